@@ -1,235 +1,2065 @@
-use crate::{graph::load_graph, Result};
-use std::{path::PathBuf, time::Instant};
+//! Graph algorithms built on the [`ligra`](crate::ligra) engine.
+//!
+//! Every algorithm takes a graph and a configuration and returns plain per-node values.
+//! The `*_with` variants additionally take a [`Workspace`] and a [`RunControl`]
+//! to reuse buffers across runs and to limit, observe or cancel a run.
+//! Algorithms run on the current rayon pool, use [`rayon::ThreadPool::install`]
+//! to run them on a different one.
 
-pub fn run_cc(input: PathBuf) -> Result<()> {
-    let graph = load_graph(input)?;
+pub use control::{Progress, RunControl, RunStats, Running, Stop};
+pub use output::{write_edge_list, write_output, OutputFormat, OutputValue};
+pub use parallelism::Parallelism;
 
-    let start = Instant::now();
+use crate::{
+    graph::{AdjacencyGraph, FilteredGraph, Graph, Weight, WeightedGraph},
+    ligra::Workspace,
+    Result,
+};
+use atomic_float::AtomicF64;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicI64, AtomicUsize};
 
-    let cc = cc::cc(graph);
+#[path = "control.rs"]
+mod control;
+#[path = "output.rs"]
+mod output;
+#[path = "parallelism.rs"]
+mod parallelism;
 
-    println!("cc done with {} nodes: {:?}", cc.len(), start.elapsed());
+/// The result of an algorithm for every node, together with statistics about the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeValues<T> {
+    /// Indexed by node id.
+    pub values: Vec<T>,
+    pub stats: RunStats,
+}
+
+impl<T: Send> NodeValues<T> {
+    fn from_atomics<A: Send>(atomics: Vec<A>, into_inner: fn(A) -> T, stats: RunStats) -> Self {
+        Self {
+            values: atomics.into_par_iter().map(into_inner).collect(),
+            stats,
+        }
+    }
+}
+
+/// Configuration of [`connected_components`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CcConfig {}
+
+/// Configuration of [`bfs`] and [`bfs_tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BfsConfig {
+    /// The nodes to start the traversal at, every node is reached from its nearest source.
+    pub sources: Vec<usize>,
+}
+
+impl BfsConfig {
+    pub fn new(source: usize) -> Self {
+        Self {
+            sources: vec![source],
+        }
+    }
+
+    /// Starts the traversal at all `sources` at once, duplicates are ignored.
+    pub fn from_sources(sources: impl Into<Vec<usize>>) -> Self {
+        Self {
+            sources: sources.into(),
+        }
+    }
+}
+
+/// The breadth-first search tree computed by [`bfs_tree`].
+///
+/// Sources are their own parent and have a distance of 0,
+/// all values of unreached nodes are `usize::MAX`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BfsTree {
+    /// Indexed by node id.
+    pub parents: Vec<usize>,
+    /// The number of hops from the nearest source.
+    pub distances: Vec<usize>,
+    /// The source that reached the node. If several sources are equally near,
+    /// any of them may be recorded.
+    pub sources: Vec<usize>,
+    pub stats: RunStats,
+}
+
+impl BfsTree {
+    /// The relationships of the tree as `(parent, child)` pairs, ordered by child.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.parents
+            .iter()
+            .enumerate()
+            .filter(|&(child, &parent)| parent != usize::MAX && parent != child)
+            .map(|(child, &parent)| (parent, child))
+    }
+}
+
+/// The sources of the shortest paths that [`betweenness_centrality`] counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BcSources {
+    /// Every node, which gives the exact centrality.
+    All,
+    /// The given nodes, duplicates are ignored.
+    Nodes(Vec<usize>),
+    /// This many distinct nodes, chosen at random with [`BcConfig::seed`].
+    /// The scores are scaled up to estimate the exact centrality.
+    Sample(usize),
+}
+
+/// Configuration of [`betweenness_centrality`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BcConfig {
+    pub sources: BcSources,
+    /// Determines the sampled sources, the same seed chooses the same sources.
+    pub seed: u64,
+}
+
+impl Default for BcConfig {
+    fn default() -> Self {
+        Self {
+            sources: BcSources::All,
+            seed: 0,
+        }
+    }
+}
+
+impl BcConfig {
+    pub fn new(source: usize) -> Self {
+        Self::from_sources(vec![source])
+    }
+
+    pub fn from_sources(sources: impl Into<Vec<usize>>) -> Self {
+        Self {
+            sources: BcSources::Nodes(sources.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn sampled(samples: usize) -> Self {
+        Self {
+            sources: BcSources::Sample(samples),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Configuration of [`radii`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RadiiConfig {
+    /// The number of sources that are traversed at once, at most 64.
+    pub samples: usize,
+    /// Determines the sampled sources, the same seed chooses the same sources.
+    pub seed: u64,
+}
+
+impl Default for RadiiConfig {
+    fn default() -> Self {
+        Self {
+            samples: 64,
+            seed: 0,
+        }
+    }
+}
+
+impl RadiiConfig {
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// The eccentricity estimates computed by [`radii`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadiiEstimate {
+    /// The largest distance from any sampled source to the node, `usize::MAX` if no source
+    /// reaches it. In undirected graphs, this is a lower bound of the eccentricity.
+    pub eccentricities: Vec<usize>,
+    /// The largest of the eccentricity estimates, a lower bound of the diameter.
+    pub diameter: usize,
+    pub stats: RunStats,
+}
+
+/// Configuration of [`k_core`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct KCoreConfig {}
+
+/// The core decomposition computed by [`k_core`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct KCore {
+    /// The largest `k` such that the node is part of the `k`-core.
+    /// If the run was stopped, nodes that were not peeled yet have a lower bound.
+    pub coreness: Vec<usize>,
+    /// The largest coreness of any node.
+    pub degeneracy: usize,
+    pub stats: RunStats,
+}
+
+impl KCore {
+    /// The `k`-core of `graph`, the subgraph induced by all nodes with a coreness of at least `k`.
+    ///
+    /// Node ids are kept, nodes outside of the core have no relationships.
+    pub fn subgraph<G: Graph + Sync>(&self, graph: &G, k: usize) -> AdjacencyGraph {
+        let coreness = self.coreness.clone();
+        let core = FilteredGraph::new(graph).with_node_filter(move |node| coreness[node] >= k);
+        AdjacencyGraph::from_graph(&core)
+    }
+}
+
+/// Configuration of [`maximal_independent_set`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MisConfig {
+    /// Determines the random priorities of the nodes, the same seed chooses the same set.
+    pub seed: u64,
+}
+
+impl MisConfig {
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Configuration of [`bellman_ford`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BellmanFordConfig {
+    pub source: usize,
+}
+
+impl BellmanFordConfig {
+    pub fn new(source: usize) -> Self {
+        Self { source }
+    }
+}
+
+/// Configuration of [`delta_stepping`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeltaSteppingConfig {
+    pub source: usize,
+    /// The range of distances that share a bucket, `1` processes every distance on its own.
+    pub delta: Weight,
+}
+
+impl DeltaSteppingConfig {
+    pub fn new(source: usize) -> Self {
+        Self { source, delta: 1 }
+    }
+
+    pub fn with_delta(mut self, delta: Weight) -> Self {
+        self.delta = delta;
+        self
+    }
+}
+
+/// The result of a single-source shortest path search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortestPaths {
+    /// The length of a shortest path from the source, `Weight::MAX` if the node is not reached.
+    pub distances: Vec<Weight>,
+    /// The previous node on a shortest path from the source, `usize::MAX` for the source
+    /// and for unreached nodes. Only complete if the run was not stopped.
+    pub predecessors: Vec<usize>,
+    /// Whether a cycle of negative length is reachable from the source, only detected by
+    /// [`bellman_ford`]. If so, the distances are meaningless and there are no predecessors.
+    pub negative_cycle: bool,
+    pub stats: RunStats,
+}
+
+/// Configuration of [`triangle_count`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TriangleConfig {
+    /// Also count the triangles of every node.
+    pub node_counts: bool,
+}
+
+impl TriangleConfig {
+    pub fn with_node_counts(mut self, node_counts: bool) -> Self {
+        self.node_counts = node_counts;
+        self
+    }
+}
+
+/// The triangles found by [`triangle_count`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triangles {
+    /// The number of distinct triangles in the graph.
+    pub count: usize,
+    /// The number of triangles every node is part of, if configured.
+    pub node_counts: Option<Vec<usize>>,
+    pub stats: RunStats,
+}
+
+/// Configuration of [`page_rank`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct PageRankConfig {
+    /// The probability of following a relationship instead of jumping to a random node.
+    pub damping: f64,
+    /// Stop once the ranks of an iteration changed by less than this in total (L1 norm).
+    pub tolerance: f64,
+    /// Stop after this many iterations, even if the ranks did not converge.
+    pub max_iterations: usize,
+}
+
+impl Default for PageRankConfig {
+    fn default() -> Self {
+        Self {
+            damping: 0.85,
+            tolerance: 1E-7,
+            max_iterations: 100,
+        }
+    }
+}
+
+impl PageRankConfig {
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+}
+
+/// Configuration of [`page_rank_delta`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct PageRankDeltaConfig {
+    /// The probability of following a relationship instead of jumping to a random node.
+    pub damping: f64,
+    /// Stop once the rank changes of an iteration sum up to less than this.
+    pub tolerance: f64,
+    /// Stop after this many iterations, even if the ranks did not converge.
+    pub max_iterations: usize,
+}
+
+impl Default for PageRankDeltaConfig {
+    fn default() -> Self {
+        Self {
+            damping: 0.85,
+            tolerance: 1E-7,
+            max_iterations: 100,
+        }
+    }
+}
+
+impl PageRankDeltaConfig {
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+}
+
+/// Labels every node with the smallest node id that reaches it, which is the
+/// component id in an undirected (symmetric) graph.
+pub fn connected_components<G: Graph + Sync>(graph: &G, config: &CcConfig) -> NodeValues<usize> {
+    connected_components_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`connected_components`], but with explicit buffers and run control.
+pub fn connected_components_with<G: Graph + Sync>(
+    graph: &G,
+    _config: &CcConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> NodeValues<usize> {
+    let (ids, stats) = cc::cc(graph, workspace, control);
+    NodeValues::from_atomics(ids, AtomicUsize::into_inner, stats)
+}
+
+/// Returns the parent of every node in a breadth-first search tree rooted at the sources.
+/// Sources are their own parent, unreached nodes have `usize::MAX` as parent.
+///
+/// # Panics
+///
+/// If a source is not a node of the graph.
+pub fn bfs<G: Graph + Sync>(graph: &G, config: &BfsConfig) -> NodeValues<usize> {
+    bfs_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`bfs`], but with explicit buffers and run control.
+pub fn bfs_with<G: Graph + Sync>(
+    graph: &G,
+    config: &BfsConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> NodeValues<usize> {
+    let tree = bfs_tree_with(graph, config, workspace, control);
+    NodeValues {
+        values: tree.parents,
+        stats: tree.stats,
+    }
+}
+
+/// Like [`bfs`], but additionally returns the distance of every node
+/// and the source that reached it.
+pub fn bfs_tree<G: Graph + Sync>(graph: &G, config: &BfsConfig) -> BfsTree {
+    bfs_tree_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`bfs_tree`], but with explicit buffers and run control.
+pub fn bfs_tree_with<G: Graph + Sync>(
+    graph: &G,
+    config: &BfsConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> BfsTree {
+    for &source in config.sources.iter() {
+        assert!(
+            source < graph.node_count(),
+            "source {} is not a node of a graph with {} nodes",
+            source,
+            graph.node_count()
+        );
+    }
+    let (bfs, stats) = bfs::bfs(graph, &config.sources, workspace, control);
+    let values = |atomics: Vec<AtomicUsize>| {
+        atomics
+            .into_par_iter()
+            .map(AtomicUsize::into_inner)
+            .collect()
+    };
+    BfsTree {
+        parents: values(bfs.parents),
+        distances: values(bfs.distances),
+        sources: values(bfs.sources),
+        stats,
+    }
+}
+
+/// Computes the page rank of every node, only propagating changes
+/// of nodes whose rank changed significantly.
+///
+/// Like in [`page_rank`], the rank of nodes without relationships is spread over all nodes.
+/// Smaller changes are held back until they add up, so the ranks sum up to about 1.
+pub fn page_rank_delta<G: Graph + Sync>(
+    graph: &G,
+    config: &PageRankDeltaConfig,
+) -> NodeValues<f64> {
+    page_rank_delta_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
 
+/// Like [`page_rank_delta`], but with explicit buffers and run control.
+pub fn page_rank_delta_with<G: Graph + Sync>(
+    graph: &G,
+    config: &PageRankDeltaConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> NodeValues<f64> {
+    let (ranks, stats) = pagerank_delta::page_rank_delta(graph, config, workspace, control);
+    NodeValues::from_atomics(ranks, AtomicF64::into_inner, stats)
+}
+
+/// Computes the page rank of every node by power iteration over all nodes.
+///
+/// The rank of nodes without relationships is spread over all nodes,
+/// so that the ranks always sum up to 1.
+pub fn page_rank<G: Graph + Sync>(graph: &G, config: &PageRankConfig) -> NodeValues<f64> {
+    page_rank_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`page_rank`], but with explicit buffers and run control.
+pub fn page_rank_with<G: Graph + Sync>(
+    graph: &G,
+    config: &PageRankConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> NodeValues<f64> {
+    let (values, stats) = pagerank::page_rank(graph, config, workspace, control);
+    NodeValues { values, stats }
+}
+
+mod cc {
+    use super::{RunControl, RunStats};
+    use crate::{
+        graph::Graph,
+        ligra::{self, MapperFlags, NodeMapper, RelationshipMapper},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CC {
+        ids: Vec<AtomicUsize>,
+        prev_ids: Vec<AtomicUsize>,
+    }
+
+    impl NodeMapper for CC {
+        fn update(&self, node: usize) -> bool {
+            self.prev_ids[node].store(self.ids[node].load(Ordering::Relaxed), Ordering::Relaxed);
+            true
+        }
+
+        fn update_always_returns_true(&self) -> bool {
+            true
+        }
+    }
+
+    impl RelationshipMapper for CC {
+        fn update(&self, source: usize, target: usize) -> bool {
+            let atom = &self.ids[target];
+            let original_id = atom.load(Ordering::Relaxed);
+
+            Self::write_min(atom, self.ids[source].load(Ordering::Relaxed))
+                && original_id == self.prev_ids[target].load(Ordering::Relaxed)
+        }
+    }
+
+    impl MapperFlags for CC {
+        fn check_always_returns_true(&self) -> bool {
+            true
+        }
+
+        // concurrent updates of the same target can all see the unchanged previous id
+        fn remove_duplicates(&self) -> bool {
+            true
+        }
+    }
+
+    impl CC {
+        fn new(node_count: usize) -> Self {
+            Self {
+                ids: ligra::par_vec(node_count, AtomicUsize::new),
+                prev_ids: ligra::par_vec_with(node_count, || AtomicUsize::new(0)),
+            }
+        }
+
+        // ids only ever decrease, so the loop needs no ordering with other memory
+        fn write_min(atom: &AtomicUsize, value: usize) -> bool {
+            loop {
+                let current = atom.load(Ordering::Relaxed);
+                if value < current {
+                    if atom
+                        .compare_exchange_weak(current, value, Ordering::Relaxed, Ordering::Relaxed)
+                        .is_ok()
+                    {
+                        return true;
+                    }
+                } else {
+                    return false;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn cc<G: Graph + Sync>(
+        graph: &G,
+        workspace: &mut ligra::Workspace,
+        control: &RunControl,
+    ) -> (Vec<AtomicUsize>, RunStats) {
+        let cc = CC::new(graph.node_count());
+
+        let mut frontier = ligra::NodeSubset::full(graph.node_count());
+        let mut running = control.start();
+
+        while frontier.subset_count() != 0 && running.next_iteration(frontier.subset_count()) {
+            let next = ligra::node_filter_with(&frontier, &cc, workspace);
+            workspace.recycle(std::mem::replace(&mut frontier, next));
+            ligra::relationship_map_with(graph, &mut frontier, &cc, workspace);
+        }
+
+        (cc.ids, running.finish())
+    }
+}
+
+/// Computes the betweenness centrality of every node with the algorithm of Brandes.
+///
+/// The score of a node sums, over all pairs of a source and another node, the fraction
+/// of shortest paths between the two that pass through it. Undirected graphs count
+/// every pair in both directions.
+///
+/// # Panics
+///
+/// If a source is not a node of the graph.
+pub fn betweenness_centrality<G: Graph + Sync>(graph: &G, config: &BcConfig) -> NodeValues<f64> {
+    betweenness_centrality_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`betweenness_centrality`], but with explicit buffers and run control.
+pub fn betweenness_centrality_with<G: Graph + Sync>(
+    graph: &G,
+    config: &BcConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> NodeValues<f64> {
+    let node_count = graph.node_count();
+    let (sources, scale) = match &config.sources {
+        BcSources::All => ((0..node_count).collect(), false),
+        BcSources::Nodes(nodes) => {
+            for &source in nodes.iter() {
+                assert!(
+                    source < node_count,
+                    "source {} is not a node of a graph with {} nodes",
+                    source,
+                    node_count
+                );
+            }
+            let mut sources = nodes.clone();
+            sources.sort_unstable();
+            sources.dedup();
+            (sources, false)
+        }
+        BcSources::Sample(samples) => {
+            let sources = sample_nodes(node_count, *samples, config.seed);
+            (sources, true)
+        }
+    };
+
+    let (mut values, completed, stats) = bc::bc(graph, &sources, workspace, control);
+    // a stopped run only has the scores of the sources that were completed
+    if scale && completed > 0 {
+        let scale = node_count as f64 / completed as f64;
+        values.par_iter_mut().for_each(|value| *value *= scale);
+    }
+    NodeValues { values, stats }
+}
+
+/// Estimates the eccentricity of every node and the diameter of the graph
+/// with a breadth-first search from up to 64 random sources at once.
+///
+/// # Panics
+///
+/// If less than 1 or more than 64 samples are configured, as the sources
+/// have to fit into the 64 bits of the visited masks.
+pub fn radii<G: Graph + Sync>(graph: &G, config: &RadiiConfig) -> RadiiEstimate {
+    radii_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`radii`], but with explicit buffers and run control.
+pub fn radii_with<G: Graph + Sync>(
+    graph: &G,
+    config: &RadiiConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> RadiiEstimate {
+    assert!(
+        (1..=64).contains(&config.samples),
+        "radii needs between 1 and 64 samples, got {}",
+        config.samples
+    );
+    let sources = sample_nodes(graph.node_count(), config.samples, config.seed);
+    let (eccentricities, stats) = radii::radii(graph, &sources, workspace, control);
+    let eccentricities: Vec<usize> = eccentricities
+        .into_par_iter()
+        .map(AtomicUsize::into_inner)
+        .collect();
+    let diameter = eccentricities
+        .par_iter()
+        .copied()
+        .filter(|&eccentricity| eccentricity != usize::MAX)
+        .max()
+        .unwrap_or(0);
+    RadiiEstimate {
+        eccentricities,
+        diameter,
+        stats,
+    }
+}
+
+/// Computes the coreness of every node by repeatedly peeling the nodes of smallest degree.
+///
+/// The graph has to be symmetric, i.e. every relationship has to exist in both directions.
+pub fn k_core<G: Graph + Sync>(graph: &G, config: &KCoreConfig) -> KCore {
+    k_core_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`k_core`], but with explicit buffers and run control.
+pub fn k_core_with<G: Graph + Sync>(
+    graph: &G,
+    _config: &KCoreConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> KCore {
+    let (coreness, stats) = kcore::k_core(graph, workspace, control);
+    let coreness: Vec<usize> = coreness
+        .into_par_iter()
+        .map(AtomicUsize::into_inner)
+        .collect();
+    let degeneracy = coreness.par_iter().copied().max().unwrap_or(0);
+    KCore {
+        coreness,
+        degeneracy,
+        stats,
+    }
+}
+
+/// Computes a maximal independent set, `true` for every node in the set.
+///
+/// In every round, the undecided nodes with a higher random priority than all of their
+/// undecided neighbors join the set and their neighbors are excluded from it.
+/// The graph has to be symmetric, self loops are ignored.
+/// If the run is stopped, the set is independent but not necessarily maximal.
+pub fn maximal_independent_set<G: Graph + Sync>(graph: &G, config: &MisConfig) -> NodeValues<bool> {
+    maximal_independent_set_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`maximal_independent_set`], but with explicit buffers and run control.
+pub fn maximal_independent_set_with<G: Graph + Sync>(
+    graph: &G,
+    config: &MisConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> NodeValues<bool> {
+    let (states, stats) = mis::mis(graph, config.seed, workspace, control);
+    NodeValues::from_atomics(states, mis::into_member, stats)
+}
+
+/// Checks that no two nodes in `in_set` are neighbors and that every other node
+/// has a neighbor in the set, ignoring self loops.
+pub fn verify_independent_set<G: Graph + Sync>(graph: &G, in_set: &[bool]) -> Result<()> {
+    ensure!(
+        in_set.len() == graph.node_count(),
+        "the set has {} entries for {} nodes",
+        in_set.len(),
+        graph.node_count()
+    );
+    let neighbors = |node: usize| {
+        graph
+            .out(node)
+            .iter()
+            .enumerate()
+            .filter(move |&(index, &target)| target != node && graph.contains_out(node, index))
+            .map(|(_, &target)| target)
+    };
+
+    let conflict = (0..graph.node_count())
+        .into_par_iter()
+        .filter(|&node| in_set[node])
+        .find_map_any(|node| {
+            neighbors(node)
+                .find(|&target| in_set[target])
+                .map(|target| (node, target))
+        });
+    if let Some((node, target)) = conflict {
+        bail!(
+            "nodes {} and {} are neighbors and both in the set",
+            node,
+            target
+        );
+    }
+
+    let uncovered = (0..graph.node_count())
+        .into_par_iter()
+        .find_any(|&node| !in_set[node] && !neighbors(node).any(|target| in_set[target]));
+    if let Some(node) = uncovered {
+        bail!(
+            "node {} could be added to the set, it has no neighbor in it",
+            node
+        );
+    }
     Ok(())
 }
 
-pub fn run_bfs(input: PathBuf, source: usize) -> Result<()> {
-    let graph = load_graph(input)?;
+/// Computes the shortest paths from a single source by relaxing the relationships
+/// of all nodes whose distance changed, round by round, until no distance changes.
+///
+/// Negative weights are allowed. A negative cycle is detected if distances
+/// still change after as many rounds as there are nodes.
+///
+/// # Panics
+///
+/// If the source is not a node of the graph.
+pub fn bellman_ford<G: WeightedGraph + Sync>(
+    graph: &G,
+    config: &BellmanFordConfig,
+) -> ShortestPaths {
+    bellman_ford_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
 
-    let start = Instant::now();
+/// Like [`bellman_ford`], but with explicit buffers and run control.
+pub fn bellman_ford_with<G: WeightedGraph + Sync>(
+    graph: &G,
+    config: &BellmanFordConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> ShortestPaths {
+    assert!(
+        config.source < graph.node_count(),
+        "source {} is not a node of the graph with {} nodes",
+        config.source,
+        graph.node_count()
+    );
+    let (distances, negative_cycle, stats) =
+        bellman_ford::bellman_ford(graph, config.source, workspace, control);
+    let distances: Vec<Weight> = distances
+        .into_par_iter()
+        .map(AtomicI64::into_inner)
+        .collect();
+    let predecessors = if negative_cycle {
+        vec![usize::MAX; graph.node_count()]
+    } else {
+        sssp::predecessors(graph, &distances, config.source, workspace)
+    };
+    ShortestPaths {
+        distances,
+        predecessors,
+        negative_cycle,
+        stats,
+    }
+}
 
-    let parents = bfs::bfs(graph, source);
+/// Computes the shortest paths from a single source by settling nodes in buckets
+/// of `delta` consecutive distances, in increasing order of the distances.
+///
+/// Weights must not be negative, use [`bellman_ford`] for graphs with negative weights.
+///
+/// # Panics
+///
+/// If the source is not a node of the graph, `delta` is not positive
+/// or a relationship has a negative weight.
+pub fn delta_stepping<G: WeightedGraph + Sync>(
+    graph: &G,
+    config: &DeltaSteppingConfig,
+) -> ShortestPaths {
+    delta_stepping_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
 
-    println!(
-        "bfs done with {} nodes: {:?}",
-        parents.len(),
-        start.elapsed()
+/// Like [`delta_stepping`], but with explicit buffers and run control.
+pub fn delta_stepping_with<G: WeightedGraph + Sync>(
+    graph: &G,
+    config: &DeltaSteppingConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> ShortestPaths {
+    assert!(
+        config.source < graph.node_count(),
+        "source {} is not a node of the graph with {} nodes",
+        config.source,
+        graph.node_count()
+    );
+    assert!(
+        config.delta > 0,
+        "delta must be positive, got {}",
+        config.delta
     );
+    if let Some((node, weight)) = negative_weight(graph) {
+        panic!(
+            "delta-stepping needs non-negative weights, node {} has a relationship of weight {}",
+            node, weight
+        );
+    }
+    let (distances, stats) =
+        delta_stepping::delta_stepping(graph, config.source, config.delta, workspace, control);
+    let distances: Vec<Weight> = distances
+        .into_par_iter()
+        .map(AtomicI64::into_inner)
+        .collect();
+    let predecessors = sssp::predecessors(graph, &distances, config.source, workspace);
+    ShortestPaths {
+        distances,
+        predecessors,
+        negative_cycle: false,
+        stats,
+    }
+}
+
+/// Some node with an outgoing relationship of negative weight, together with that weight.
+fn negative_weight<G: WeightedGraph + Sync>(graph: &G) -> Option<(usize, Weight)> {
+    (0..graph.node_count())
+        .into_par_iter()
+        .find_map_any(|node| {
+            let weight = graph.out_weights(node).iter().copied().find(|&w| w < 0)?;
+            Some((node, weight))
+        })
+}
+
+/// Counts the triangles of the graph by intersecting sorted neighbor lists.
+///
+/// Every relationship is oriented from the node of lower to the node of higher degree,
+/// so that every triangle is found exactly once and high degree nodes have short lists.
+/// The graph has to be symmetric, self loops and parallel relationships are ignored.
+pub fn triangle_count<G: Graph + Sync>(graph: &G, config: &TriangleConfig) -> Triangles {
+    triangle_count_with(graph, config, &mut Workspace::new(), &RunControl::new())
+}
+
+/// Like [`triangle_count`], but with explicit buffers and run control.
+///
+/// The count runs as a single iteration, a stopped run finds no triangles.
+pub fn triangle_count_with<G: Graph + Sync>(
+    graph: &G,
+    config: &TriangleConfig,
+    workspace: &mut Workspace,
+    control: &RunControl,
+) -> Triangles {
+    let (count, node_counts, stats) =
+        triangles::count(graph, config.node_counts, workspace, control);
+    Triangles {
+        count,
+        node_counts,
+        stats,
+    }
+}
+
+/// Chooses `count` distinct nodes uniformly at random, the choice only depends on `seed`.
+fn sample_nodes(node_count: usize, count: usize, seed: u64) -> Vec<usize> {
+    let mut nodes = (0..node_count).collect::<Vec<_>>();
+    if count < node_count {
+        nodes.select_nth_unstable_by_key(count, |&node| hash(seed, node));
+        nodes.truncate(count);
+        nodes.sort_unstable();
+    }
+    nodes
+}
+
+/// Mixes `seed` and `value` into a pseudo random number (splitmix64).
+fn hash(seed: u64, value: usize) -> u64 {
+    let mut z = seed.wrapping_add((value as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+mod bfs {
+    use super::{RunControl, RunStats};
+    use crate::{
+        graph::Graph,
+        ligra::{self, MapperFlags, RelationshipMapper},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub(crate) struct Bfs {
+        pub(crate) parents: Vec<AtomicUsize>,
+        pub(crate) distances: Vec<AtomicUsize>,
+        pub(crate) sources: Vec<AtomicUsize>,
+        /// The distance of the nodes that are reached in the current round
+        distance: usize,
+    }
+
+    impl RelationshipMapper for Bfs {
+        fn update(&self, source: usize, target: usize) -> bool {
+            let visited = self.parents[target]
+                .compare_exchange(usize::MAX, source, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok();
+            if visited {
+                // only the winner of the parent writes the other values,
+                // the source of `source` was written in an earlier round
+                self.distances[target].store(self.distance, Ordering::Relaxed);
+                let origin = self.sources[source].load(Ordering::Relaxed);
+                self.sources[target].store(origin, Ordering::Relaxed);
+            }
+            visited
+        }
+    }
+
+    impl MapperFlags for Bfs {
+        fn check(&self, node: usize) -> bool {
+            self.parents[node].load(Ordering::Relaxed) == usize::MAX
+        }
+    }
+
+    impl Bfs {
+        fn new(node_count: usize) -> Self {
+            Self {
+                parents: ligra::par_vec_with(node_count, || AtomicUsize::new(usize::MAX)),
+                distances: ligra::par_vec_with(node_count, || AtomicUsize::new(usize::MAX)),
+                sources: ligra::par_vec_with(node_count, || AtomicUsize::new(usize::MAX)),
+                distance: 0,
+            }
+        }
+    }
+
+    pub(crate) fn bfs<G: Graph + Sync>(
+        graph: &G,
+        roots: &[usize],
+        workspace: &mut ligra::Workspace,
+        control: &RunControl,
+    ) -> (Bfs, RunStats) {
+        let mut bfs = Bfs::new(graph.node_count());
+
+        let mut roots = roots.to_vec();
+        roots.sort_unstable();
+        roots.dedup();
+        for &root in roots.iter() {
+            bfs.parents[root] = AtomicUsize::new(root);
+            bfs.distances[root] = AtomicUsize::new(0);
+            bfs.sources[root] = AtomicUsize::new(root);
+        }
+
+        let mut frontier = ligra::NodeSubset::sparse(graph.node_count(), roots);
+        let mut running = control.start();
+        while frontier.subset_count() != 0 && running.next_iteration(frontier.subset_count()) {
+            bfs.distance += 1;
+            ligra::relationship_map_with(graph, &mut frontier, &bfs, workspace);
+        }
+
+        (bfs, running.finish())
+    }
+}
+
+mod bc {
+    use super::{RunControl, RunStats, Running};
+    use crate::{
+        graph::{Graph, Transposed},
+        ligra::{
+            self, par_vec_with, MapperFlags, NodeMapper, NodeSubset, RelationshipMapper, Workspace,
+        },
+    };
+    use atomic_float::AtomicF64;
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct Bc {
+        /// The number of shortest paths from the source during the forward pass,
+        /// its inverse during the backward pass
+        paths: Vec<AtomicF64>,
+        dependencies: Vec<AtomicF64>,
+        visited: Vec<AtomicBool>,
+    }
+
+    /// Counts the shortest paths to the next level.
+    struct Forward<'a>(&'a Bc);
+
+    impl RelationshipMapper for Forward<'_> {
+        fn update(&self, source: usize, target: usize) -> bool {
+            let paths = self.0.paths[source].load(Ordering::Relaxed);
+            self.0.paths[target].fetch_add(paths, Ordering::Relaxed) == 0.0
+        }
+    }
+
+    impl MapperFlags for Forward<'_> {
+        fn check(&self, node: usize) -> bool {
+            !self.0.visited[node].load(Ordering::Relaxed)
+        }
+    }
+
+    /// Adds the dependencies of a level to the predecessors on the level above.
+    struct Backward<'a>(&'a Bc);
+
+    impl RelationshipMapper for Backward<'_> {
+        fn update(&self, source: usize, target: usize) -> bool {
+            let dependency = self.0.dependencies[source].load(Ordering::Relaxed);
+            self.0.dependencies[target].fetch_add(dependency, Ordering::Relaxed);
+            true
+        }
+    }
+
+    impl MapperFlags for Backward<'_> {
+        fn check(&self, node: usize) -> bool {
+            !self.0.visited[node].load(Ordering::Relaxed)
+        }
+
+        fn has_no_result(&self) -> bool {
+            true
+        }
+    }
+
+    struct Visit<'a>(&'a Bc);
+
+    impl NodeMapper for Visit<'_> {
+        fn update(&self, node: usize) -> bool {
+            self.0.visited[node].store(true, Ordering::Relaxed);
+            true
+        }
+    }
+
+    /// Completes the dependencies of a level once all levels below were added.
+    struct Resolve<'a>(&'a Bc);
+
+    impl NodeMapper for Resolve<'_> {
+        fn update(&self, node: usize) -> bool {
+            self.0.visited[node].store(true, Ordering::Relaxed);
+            let inverse_paths = self.0.paths[node].load(Ordering::Relaxed);
+            self.0.dependencies[node].fetch_add(inverse_paths, Ordering::Relaxed);
+            true
+        }
+    }
+
+    impl Bc {
+        fn new(node_count: usize) -> Self {
+            Self {
+                paths: par_vec_with(node_count, AtomicF64::default),
+                dependencies: par_vec_with(node_count, AtomicF64::default),
+                visited: par_vec_with(node_count, AtomicBool::default),
+            }
+        }
+
+        /// Adds the dependency of every node on `source` to `scores`.
+        /// Returns false without adding anything if the run was stopped.
+        fn accumulate<G: Graph + Sync>(
+            &self,
+            graph: &G,
+            source: usize,
+            scores: &mut [f64],
+            workspace: &mut Workspace,
+            running: &mut Running<'_>,
+        ) -> bool {
+            let node_count = graph.node_count();
+            (0..node_count).into_par_iter().for_each(|node| {
+                self.paths[node].store(0.0, Ordering::Relaxed);
+                self.dependencies[node].store(0.0, Ordering::Relaxed);
+                self.visited[node].store(false, Ordering::Relaxed);
+            });
+            self.paths[source].store(1.0, Ordering::Relaxed);
+            self.visited[source].store(true, Ordering::Relaxed);
+
+            // forward pass, keeping every level of the search for the backward pass
+            let mut levels = Vec::new();
+            let mut frontier = NodeSubset::single(node_count, source);
+            while frontier.subset_count() != 0 {
+                if !running.next_iteration(frontier.subset_count()) {
+                    levels
+                        .into_iter()
+                        .for_each(|level| workspace.recycle(level));
+                    return false;
+                }
+                levels.push(frontier.clone());
+                ligra::relationship_map_with(graph, &mut frontier, &Forward(self), workspace);
+                ligra::node_map(&frontier, &Visit(self));
+            }
+            workspace.recycle(frontier);
+
+            // unreached nodes count as visited, so that the backward pass ignores them
+            (0..node_count).into_par_iter().for_each(|node| {
+                let paths = self.paths[node].load(Ordering::Relaxed);
+                self.visited[node].store(paths == 0.0, Ordering::Relaxed);
+                if paths != 0.0 {
+                    self.paths[node].store(1.0 / paths, Ordering::Relaxed);
+                }
+            });
+
+            // backward pass, from the deepest level up to the source
+            let transposed = Transposed::new(graph);
+            let mut children = levels.pop().expect("the source is the first level");
+            ligra::node_map(&children, &Resolve(self));
+            while let Some(level) = levels.pop() {
+                ligra::relationship_map_with(
+                    &transposed,
+                    &mut children,
+                    &Backward(self),
+                    workspace,
+                );
+                workspace.recycle(children);
+                ligra::node_map(&level, &Resolve(self));
+                children = level;
+            }
+            workspace.recycle(children);
+
+            scores
+                .par_iter_mut()
+                .enumerate()
+                .filter(|&(node, _)| node != source)
+                .for_each(|(node, score)| {
+                    let inverse_paths = self.paths[node].load(Ordering::Relaxed);
+                    if inverse_paths != 0.0 {
+                        let dependency = self.dependencies[node].load(Ordering::Relaxed);
+                        *score += (dependency - inverse_paths) / inverse_paths;
+                    }
+                });
+            true
+        }
+    }
+
+    pub(crate) fn bc<G: Graph + Sync>(
+        graph: &G,
+        sources: &[usize],
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<f64>, usize, RunStats) {
+        let bc = Bc::new(graph.node_count());
+        let mut scores = vec![0.0; graph.node_count()];
+        let mut running = control.start();
+
+        let mut completed = 0;
+        for &source in sources {
+            if !bc.accumulate(graph, source, &mut scores, workspace, &mut running) {
+                break;
+            }
+            completed += 1;
+        }
+
+        (scores, completed, running.finish())
+    }
+}
+
+mod radii {
+    use super::{RunControl, RunStats};
+    use crate::{
+        graph::Graph,
+        ligra::{
+            self, par_vec_with, MapperFlags, NodeMapper, NodeSubset, RelationshipMapper, Workspace,
+        },
+    };
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+    struct Radii {
+        /// Bit `i` is set if the node was reached from the `i`-th source
+        visited: Vec<AtomicU64>,
+        /// The masks of the current round, applied to `visited` after the round
+        next_visited: Vec<AtomicU64>,
+        eccentricities: Vec<AtomicUsize>,
+        round: usize,
+    }
+
+    impl RelationshipMapper for Radii {
+        fn update(&self, source: usize, target: usize) -> bool {
+            let visited = self.visited[target].load(Ordering::Relaxed);
+            let reached = visited | self.visited[source].load(Ordering::Relaxed);
+            if visited == reached {
+                return false;
+            }
+            self.next_visited[target].fetch_or(reached, Ordering::Relaxed);
+            // the first update of the round adds the target to the next frontier
+            self.eccentricities[target].swap(self.round, Ordering::Relaxed) != self.round
+        }
+    }
+
+    impl MapperFlags for Radii {}
+
+    /// Starts the round of a frontier node with its current mask.
+    struct Prepare<'a>(&'a Radii);
+
+    impl NodeMapper for Prepare<'_> {
+        fn update(&self, node: usize) -> bool {
+            let visited = self.0.visited[node].load(Ordering::Relaxed);
+            self.0.next_visited[node].store(visited, Ordering::Relaxed);
+            true
+        }
+    }
+
+    /// Applies the mask of the round to a node that was reached by new sources.
+    struct Commit<'a>(&'a Radii);
+
+    impl NodeMapper for Commit<'_> {
+        fn update(&self, node: usize) -> bool {
+            let next_visited = self.0.next_visited[node].load(Ordering::Relaxed);
+            self.0.visited[node].store(next_visited, Ordering::Relaxed);
+            true
+        }
+    }
+
+    impl Radii {
+        fn new(node_count: usize) -> Self {
+            Self {
+                visited: par_vec_with(node_count, AtomicU64::default),
+                next_visited: par_vec_with(node_count, AtomicU64::default),
+                eccentricities: par_vec_with(node_count, || AtomicUsize::new(usize::MAX)),
+                round: 0,
+            }
+        }
+    }
+
+    pub(crate) fn radii<G: Graph + Sync>(
+        graph: &G,
+        sources: &[usize],
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<AtomicUsize>, RunStats) {
+        let mut radii = Radii::new(graph.node_count());
+        for (i, &source) in sources.iter().enumerate() {
+            radii.visited[source] = AtomicU64::new(1 << i);
+            radii.eccentricities[source] = AtomicUsize::new(0);
+        }
+
+        let mut frontier = NodeSubset::sparse(graph.node_count(), sources);
+        let mut running = control.start();
+        while frontier.subset_count() != 0 && running.next_iteration(frontier.subset_count()) {
+            radii.round += 1;
+            ligra::node_map(&frontier, &Prepare(&radii));
+            ligra::relationship_map_with(graph, &mut frontier, &radii, workspace);
+            ligra::node_map(&frontier, &Commit(&radii));
+        }
+
+        (radii.eccentricities, running.finish())
+    }
+}
+
+mod kcore {
+    use super::{RunControl, RunStats};
+    use crate::{
+        graph::Graph,
+        ligra::{self, par_vec, Buckets, NodeMapper, RelationshipReducer, Workspace},
+    };
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Sets the coreness of the nodes that are peeled.
+    struct Peel<'a> {
+        coreness: &'a [AtomicUsize],
+        k: usize,
+    }
+
+    impl NodeMapper for Peel<'_> {
+        fn update(&self, node: usize) -> bool {
+            self.coreness[node].store(self.k, Ordering::Relaxed);
+            true
+        }
+    }
+
+    /// Counts the peeled neighbors of every node that is still in a bucket.
+    struct PeeledNeighbors<'a>(&'a Buckets);
+
+    impl RelationshipReducer for PeeledNeighbors<'_> {
+        type Value = usize;
+
+        fn identity(&self) -> usize {
+            0
+        }
+
+        fn map(&self, _source: usize, _target: usize) -> usize {
+            1
+        }
+
+        fn reduce(&self, left: usize, right: usize) -> usize {
+            left + right
+        }
+
+        fn check(&self, node: usize) -> bool {
+            self.0.bucket(node) != Buckets::NONE
+        }
+    }
+
+    pub(crate) fn k_core<G: Graph + Sync>(
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<AtomicUsize>, RunStats) {
+        let node_count = graph.node_count();
+        let coreness = par_vec(node_count, |_| AtomicUsize::new(0));
+
+        // the bucket of a node is the number of its neighbors that were not peeled yet,
+        // but never less than the `k` that is currently peeled
+        let mut buckets = Buckets::new(node_count, |node| graph.out_degree(node));
+        let mut running = control.start();
+        let mut k = 0;
+        while let Some((bucket, mut frontier)) = buckets.next_bucket_with(workspace) {
+            k = bucket;
+            ligra::node_map(
+                &frontier,
+                &Peel {
+                    coreness: &coreness,
+                    k,
+                },
+            );
+            if !running.next_iteration(frontier.subset_count()) {
+                break;
+            }
+
+            let peeled = ligra::relationship_map_reduce_with(
+                graph,
+                &mut frontier,
+                &PeeledNeighbors(&buckets),
+                workspace,
+            );
+            workspace.recycle(frontier);
+
+            let moved = peeled
+                .nodes()
+                .par_iter()
+                .zip(peeled.values().par_iter())
+                .filter_map(|(&node, &count)| {
+                    let degree = buckets.bucket(node);
+                    let new_degree = degree.saturating_sub(count).max(k);
+                    (new_degree != degree).then_some((node, new_degree))
+                })
+                .collect::<Vec<_>>();
+            buckets.update_batch(&moved);
+        }
+
+        // nodes that were not peeled yet are at least in the core of the last bucket
+        (0..node_count).into_par_iter().for_each(|node| {
+            if buckets.bucket(node) != Buckets::NONE {
+                coreness[node].store(k, Ordering::Relaxed);
+            }
+        });
+
+        (coreness, running.finish())
+    }
+}
+
+mod mis {
+    use super::{hash, RunControl, RunStats};
+    use crate::{
+        graph::Graph,
+        ligra::{
+            self, par_vec, MapperFlags, NodeMapper, NodeSubset, RelationshipMapper,
+            RelationshipReducer, Workspace,
+        },
+    };
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+    const UNDECIDED: u8 = 0;
+    const IN_SET: u8 = 1;
+    const EXCLUDED: u8 = 2;
+
+    pub(crate) fn into_member(state: AtomicU8) -> bool {
+        state.into_inner() == IN_SET
+    }
+
+    struct Mis {
+        states: Vec<AtomicU8>,
+        /// Ties of the hashed priorities are broken by the node id
+        priorities: Vec<(u64, usize)>,
+        /// The last round in which the node had an undecided neighbor of higher priority
+        beaten: Vec<AtomicUsize>,
+        round: usize,
+    }
+
+    /// Finds the targets that have an undecided neighbor of higher priority.
+    struct Beat<'a>(&'a Mis);
+
+    impl RelationshipReducer for Beat<'_> {
+        type Value = bool;
+
+        fn identity(&self) -> bool {
+            false
+        }
+
+        fn map(&self, source: usize, target: usize) -> bool {
+            self.0.priorities[source] > self.0.priorities[target]
+        }
+
+        fn reduce(&self, left: bool, right: bool) -> bool {
+            left || right
+        }
+
+        fn check(&self, target: usize) -> bool {
+            self.0.states[target].load(Ordering::Relaxed) == UNDECIDED
+        }
+    }
+
+    /// Adds the undecided nodes that were not beaten to the set.
+    struct Join<'a>(&'a Mis);
+
+    impl NodeMapper for Join<'_> {
+        fn update(&self, node: usize) -> bool {
+            if self.0.beaten[node].load(Ordering::Relaxed) == self.0.round {
+                return false;
+            }
+            self.0.states[node].store(IN_SET, Ordering::Relaxed);
+            true
+        }
+    }
+
+    /// Excludes the neighbors of the nodes that joined the set.
+    struct Exclude<'a>(&'a Mis);
+
+    impl RelationshipMapper for Exclude<'_> {
+        fn update(&self, _source: usize, target: usize) -> bool {
+            self.0.states[target].store(EXCLUDED, Ordering::Relaxed);
+            false
+        }
+    }
+
+    impl MapperFlags for Exclude<'_> {
+        fn check(&self, target: usize) -> bool {
+            self.0.states[target].load(Ordering::Relaxed) == UNDECIDED
+        }
+
+        fn has_no_result(&self) -> bool {
+            true
+        }
+    }
+
+    /// Keeps the nodes that are still undecided.
+    struct Undecided<'a>(&'a Mis);
+
+    impl NodeMapper for Undecided<'_> {
+        fn update(&self, node: usize) -> bool {
+            self.0.states[node].load(Ordering::Relaxed) == UNDECIDED
+        }
+    }
+
+    pub(crate) fn mis<G: Graph + Sync>(
+        graph: &G,
+        seed: u64,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<AtomicU8>, RunStats) {
+        let node_count = graph.node_count();
+        let mut mis = Mis {
+            states: par_vec(node_count, |_| AtomicU8::new(UNDECIDED)),
+            priorities: par_vec(node_count, |node| (hash(seed, node), node)),
+            beaten: par_vec(node_count, |_| AtomicUsize::new(0)),
+            round: 0,
+        };
+
+        let mut undecided = NodeSubset::full(node_count);
+        let mut running = control.start();
+        while !undecided.is_empty() && running.next_iteration(undecided.subset_count()) {
+            mis.round += 1;
+
+            let mut frontier = undecided.clone();
+            let beaten =
+                ligra::relationship_map_reduce_with(graph, &mut frontier, &Beat(&mis), workspace);
+            workspace.recycle(frontier);
+            beaten.nodes().par_iter().for_each(|&node| {
+                mis.beaten[node].store(mis.round, Ordering::Relaxed);
+            });
+
+            let mut joined = ligra::node_filter_with(&undecided, &Join(&mis), workspace);
+            ligra::relationship_map_with(graph, &mut joined, &Exclude(&mis), workspace);
+            workspace.recycle(joined);
+
+            let remaining = ligra::node_filter_with(&undecided, &Undecided(&mis), workspace);
+            workspace.recycle(std::mem::replace(&mut undecided, remaining));
+        }
+
+        (mis.states, running.finish())
+    }
+}
+
+mod sssp {
+    use crate::{
+        graph::{Weight, WeightedGraph},
+        ligra::{
+            self, par_vec_with, MapperFlags, NodeSubset, WeightedRelationshipMapper, Workspace,
+        },
+    };
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Follows the relationships that lie on a shortest path.
+    struct Tight<'a> {
+        distances: &'a [Weight],
+        predecessors: Vec<AtomicUsize>,
+        source: usize,
+    }
+
+    impl WeightedRelationshipMapper for Tight<'_> {
+        fn update(&self, source: usize, target: usize, weight: Weight) -> bool {
+            self.distances[source].checked_add(weight) == Some(self.distances[target])
+                && self.predecessors[target]
+                    .compare_exchange(usize::MAX, source, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+        }
+    }
+
+    impl MapperFlags for Tight<'_> {
+        fn check(&self, target: usize) -> bool {
+            target != self.source && self.predecessors[target].load(Ordering::Relaxed) == usize::MAX
+        }
+    }
+
+    /// Builds a shortest path tree from final distances with a breadth-first search
+    /// over the relationships that lie on a shortest path.
+    ///
+    /// Searching instead of picking any such relationship keeps the tree free of
+    /// cycles when there are relationships of zero weight.
+    pub(crate) fn predecessors<G: WeightedGraph + Sync>(
+        graph: &G,
+        distances: &[Weight],
+        source: usize,
+        workspace: &mut Workspace,
+    ) -> Vec<usize> {
+        let tight = Tight {
+            distances,
+            predecessors: par_vec_with(graph.node_count(), || AtomicUsize::new(usize::MAX)),
+            source,
+        };
+        let mut frontier = NodeSubset::single(graph.node_count(), source);
+        while !frontier.is_empty() {
+            ligra::weighted_relationship_map_with(graph, &mut frontier, &tight, workspace);
+        }
+        workspace.recycle(frontier);
+        tight
+            .predecessors
+            .into_par_iter()
+            .map(AtomicUsize::into_inner)
+            .collect()
+    }
+}
+
+mod bellman_ford {
+    use super::{RunControl, RunStats};
+    use crate::{
+        graph::{Weight, WeightedGraph},
+        ligra::{
+            self, par_vec_with, MapperFlags, NodeSubset, WeightedRelationshipMapper, Workspace,
+        },
+    };
+    use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+    struct BellmanFord {
+        distances: Vec<AtomicI64>,
+        /// The last round in which the distance of the node decreased
+        changed: Vec<AtomicUsize>,
+        round: usize,
+    }
+
+    impl WeightedRelationshipMapper for BellmanFord {
+        fn update(&self, source: usize, target: usize, weight: Weight) -> bool {
+            let distance = self.distances[source]
+                .load(Ordering::Relaxed)
+                .saturating_add(weight);
+            if self.distances[target].fetch_min(distance, Ordering::Relaxed) <= distance {
+                return false;
+            }
+            // the first decrease of the round adds the target to the next frontier
+            self.changed[target].swap(self.round, Ordering::Relaxed) != self.round
+        }
+    }
+
+    impl MapperFlags for BellmanFord {}
+
+    pub(crate) fn bellman_ford<G: WeightedGraph + Sync>(
+        graph: &G,
+        source: usize,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<AtomicI64>, bool, RunStats) {
+        let node_count = graph.node_count();
+        let mut bellman_ford = BellmanFord {
+            distances: par_vec_with(node_count, || AtomicI64::new(Weight::MAX)),
+            changed: par_vec_with(node_count, || AtomicUsize::new(0)),
+            round: 0,
+        };
+        bellman_ford.distances[source] = AtomicI64::new(0);
+
+        let mut negative_cycle = false;
+        let mut frontier = NodeSubset::single(node_count, source);
+        let mut running = control.start();
+        while !frontier.is_empty() {
+            // without negative cycles, all distances are final after `node_count - 1` rounds
+            if bellman_ford.round == node_count {
+                negative_cycle = true;
+                break;
+            }
+            if !running.next_iteration(frontier.subset_count()) {
+                break;
+            }
+            bellman_ford.round += 1;
+            ligra::weighted_relationship_map_with(graph, &mut frontier, &bellman_ford, workspace);
+        }
+        workspace.recycle(frontier);
+
+        (bellman_ford.distances, negative_cycle, running.finish())
+    }
+}
+
+mod delta_stepping {
+    use super::{RunControl, RunStats};
+    use crate::{
+        graph::{Weight, WeightedGraph},
+        ligra::{self, par_vec_with, Buckets, MapperFlags, WeightedRelationshipMapper, Workspace},
+    };
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+    struct DeltaStepping {
+        distances: Vec<AtomicI64>,
+        /// The last round in which the distance of the node decreased
+        changed: Vec<AtomicUsize>,
+        round: usize,
+    }
+
+    impl WeightedRelationshipMapper for DeltaStepping {
+        fn update(&self, source: usize, target: usize, weight: Weight) -> bool {
+            let distance = self.distances[source]
+                .load(Ordering::Relaxed)
+                .saturating_add(weight);
+            if self.distances[target].fetch_min(distance, Ordering::Relaxed) <= distance {
+                return false;
+            }
+            self.changed[target].swap(self.round, Ordering::Relaxed) != self.round
+        }
+    }
+
+    impl MapperFlags for DeltaStepping {}
+
+    pub(crate) fn delta_stepping<G: WeightedGraph + Sync>(
+        graph: &G,
+        source: usize,
+        delta: Weight,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<AtomicI64>, RunStats) {
+        let node_count = graph.node_count();
+        let mut delta_stepping = DeltaStepping {
+            distances: par_vec_with(node_count, || AtomicI64::new(Weight::MAX)),
+            changed: par_vec_with(node_count, || AtomicUsize::new(0)),
+            round: 0,
+        };
+        delta_stepping.distances[source] = AtomicI64::new(0);
+
+        let bucket = |distance: Weight| (distance / delta) as usize;
+        let mut buckets = Buckets::new(
+            node_count,
+            |node| {
+                if node == source {
+                    0
+                } else {
+                    Buckets::NONE
+                }
+            },
+        );
+        let mut running = control.start();
+        while let Some((_, mut frontier)) = buckets.next_bucket_with(workspace) {
+            if !running.next_iteration(frontier.subset_count()) {
+                break;
+            }
+            delta_stepping.round += 1;
+            ligra::weighted_relationship_map_with(graph, &mut frontier, &delta_stepping, workspace);
+
+            // nodes reached over light relationships return to the current bucket
+            frontier.to_sparse_with(workspace);
+            let moves = frontier
+                .nodes()
+                .par_iter()
+                .map(|&node| {
+                    let distance = delta_stepping.distances[node].load(Ordering::Relaxed);
+                    (node, bucket(distance))
+                })
+                .collect::<Vec<_>>();
+            buckets.update_batch(&moves);
+            workspace.recycle(frontier);
+        }
 
-    Ok(())
+        (delta_stepping.distances, running.finish())
+    }
 }
 
-pub fn run_page_rank_delta(input: PathBuf, max_iterations: usize) -> Result<()> {
-    let graph = load_graph(input)?;
+mod triangles {
+    use super::{RunControl, RunStats};
+    use crate::{
+        graph::{AdjacencyGraph, AdjacencyList, Graph},
+        ligra::{self, primitives, NodeSubset, NodeSubsetData, RelationshipReducer, Workspace},
+    };
+    use rayon::prelude::*;
+    use std::cmp::Ordering;
 
-    let start = Instant::now();
+    /// Keeps the relationships from every node to its neighbors that come after it in the
+    /// degree order, sorted by id. The incoming relationships of a node are then its
+    /// neighbors that come before it, also sorted by id.
+    fn orient<G: Graph + Sync>(graph: &G) -> AdjacencyGraph {
+        let rank = |node: usize| (graph.out_degree(node), node);
+        let higher = (0..graph.node_count())
+            .into_par_iter()
+            .map(|node| {
+                let mut targets = graph
+                    .out(node)
+                    .iter()
+                    .enumerate()
+                    .filter(|&(index, &target)| {
+                        rank(target) > rank(node) && graph.contains_out(node, index)
+                    })
+                    .map(|(_, &target)| target)
+                    .collect::<Vec<_>>();
+                targets.sort_unstable();
+                targets.dedup();
+                targets
+            })
+            .collect::<Vec<_>>();
 
-    let pr = pagerank_delta::page_rank_delta(graph, max_iterations);
+        let mut offsets = higher.iter().map(Vec::len).collect::<Vec<_>>();
+        primitives::scan(&mut offsets);
+        let targets = higher.into_par_iter().flatten().collect();
+        AdjacencyGraph::from(AdjacencyList::from((offsets, targets)))
+    }
 
-    println!(
-        "page rank done with {} nodes: {:?}",
-        pr.len(),
-        start.elapsed()
-    );
+    /// The number of nodes that are in both sorted lists.
+    fn intersect(mut left: &[usize], mut right: &[usize]) -> usize {
+        let mut common = 0;
+        while let (Some(&l), Some(&r)) = (left.first(), right.first()) {
+            match l.cmp(&r) {
+                Ordering::Less => left = &left[1..],
+                Ordering::Greater => right = &right[1..],
+                Ordering::Equal => {
+                    common += 1;
+                    left = &left[1..];
+                    right = &right[1..];
+                }
+            }
+        }
+        common
+    }
 
-    Ok(())
-}
+    /// Counts the triangles in which the target of an oriented relationship is the middle node.
+    struct Middle<'a>(&'a AdjacencyGraph);
 
-mod cc {
-    use crate::{
-        graph::Graph,
-        ligra::{self, NodeMapper, RelationshipMapper},
-    };
-    use std::sync::atomic::{AtomicUsize, Ordering};
+    impl RelationshipReducer for Middle<'_> {
+        type Value = usize;
 
-    struct CC {
-        ids: Vec<AtomicUsize>,
-        prev_ids: Vec<AtomicUsize>,
-    }
+        fn identity(&self) -> usize {
+            0
+        }
 
-    impl NodeMapper for CC {
-        fn update(&self, node: usize) -> bool {
-            self.prev_ids[node].store(self.ids[node].load(Ordering::SeqCst), Ordering::SeqCst);
-            true
+        fn map(&self, source: usize, target: usize) -> usize {
+            intersect(self.0.out(source), self.0.out(target))
         }
 
-        fn update_always_returns_true(&self) -> bool {
-            true
+        fn reduce(&self, left: usize, right: usize) -> usize {
+            left + right
         }
     }
 
-    impl RelationshipMapper for CC {
-        fn update(&self, source: usize, target: usize) -> bool {
-            let atom = &self.ids[target];
-            let original_id = atom.load(Ordering::SeqCst);
+    /// Counts the triangles in which the target of an oriented relationship is the highest node.
+    struct Highest<'a>(&'a AdjacencyGraph);
 
-            Self::write_min(atom, self.ids[source].load(Ordering::SeqCst))
-                && original_id == self.prev_ids[target].load(Ordering::SeqCst)
+    impl RelationshipReducer for Highest<'_> {
+        type Value = usize;
+
+        fn identity(&self) -> usize {
+            0
         }
 
-        fn check_always_returns_true(&self) -> bool {
-            true
+        fn map(&self, source: usize, target: usize) -> usize {
+            intersect(self.0.inc(source), self.0.inc(target))
         }
-    }
 
-    impl CC {
-        fn new(node_count: usize) -> Self {
-            Self {
-                ids: ligra::par_vec(node_count, AtomicUsize::new),
-                prev_ids: ligra::par_vec_with(node_count, || AtomicUsize::new(0)),
-            }
+        fn reduce(&self, left: usize, right: usize) -> usize {
+            left + right
         }
+    }
 
-        fn write_min(atom: &AtomicUsize, value: usize) -> bool {
-            loop {
-                let current = atom.load(Ordering::SeqCst);
-                if value < current {
-                    if atom
-                        .compare_exchange_weak(current, value, Ordering::SeqCst, Ordering::SeqCst)
-                        .is_ok()
-                    {
-                        return true;
-                    }
-                } else {
-                    return false;
-                }
-            }
+    fn add(node_counts: &mut [usize], counts: NodeSubsetData<usize>) {
+        for (node, &count) in counts.iter() {
+            node_counts[node] += count;
         }
     }
 
-    pub(crate) fn cc<G: Graph + Sync>(graph: G) -> Vec<AtomicUsize> {
-        let cc = CC::new(graph.node_count());
+    pub(crate) fn count<G: Graph + Sync>(
+        graph: &G,
+        node_counts: bool,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (usize, Option<Vec<usize>>, RunStats) {
+        let node_count = graph.node_count();
 
-        let mut frontier = ligra::NodeSubset::full(graph.node_count());
+        let mut running = control.start();
+        if !running.next_iteration(node_count) {
+            let node_counts = node_counts.then(|| vec![0; node_count]);
+            return (0, node_counts, running.finish());
+        }
 
-        while frontier.subset_count() != 0 {
-            frontier = ligra::node_filter(&frontier, &cc);
-            ligra::relationship_map(&graph, &mut frontier, &cc);
+        // every triangle is found once, from its lowest node
+        let oriented = orient(graph);
+        let lowest = (0..node_count)
+            .into_par_iter()
+            .map(|node| {
+                let higher = oriented.out(node);
+                higher
+                    .iter()
+                    .map(|&target| intersect(higher, oriented.out(target)))
+                    .sum::<usize>()
+            })
+            .collect::<Vec<_>>();
+        let count = lowest.par_iter().sum();
+
+        if !node_counts {
+            return (count, None, running.finish());
         }
 
-        cc.ids
+        let mut node_counts = lowest;
+        let mut all = NodeSubset::full(node_count);
+        let middle =
+            ligra::relationship_map_reduce_with(&oriented, &mut all, &Middle(&oriented), workspace);
+        add(&mut node_counts, middle);
+        let highest = ligra::relationship_map_reduce_with(
+            &oriented,
+            &mut all,
+            &Highest(&oriented),
+            workspace,
+        );
+        add(&mut node_counts, highest);
+        workspace.recycle(all);
+
+        (count, Some(node_counts), running.finish())
     }
 }
 
-mod bfs {
+mod pagerank {
+    use super::{PageRankConfig, RunControl, RunStats};
     use crate::{
         graph::Graph,
-        ligra::{self, RelationshipMapper},
+        ligra::{self, par_vec_with, MapperFlags, NodeSubset, RelationshipMapper, Workspace},
     };
-    use std::sync::atomic::{AtomicUsize, Ordering};
+    use atomic_float::AtomicF64;
+    use rayon::prelude::*;
+    use std::sync::atomic::Ordering;
 
-    struct BFS {
-        parents: Vec<AtomicUsize>,
+    /// Adds the contribution of every source to the incoming rank of its targets.
+    struct Propagate<'a> {
+        contributions: &'a [f64],
+        incoming: &'a [AtomicF64],
     }
 
-    impl RelationshipMapper for BFS {
+    impl RelationshipMapper for Propagate<'_> {
         fn update(&self, source: usize, target: usize) -> bool {
-            self.parents[target]
-                .compare_exchange(usize::MAX, source, Ordering::SeqCst, Ordering::SeqCst)
-                .is_ok()
-        }
-
-        fn check(&self, node: usize) -> bool {
-            self.parents[node].load(Ordering::SeqCst) == usize::MAX
+            self.incoming[target].fetch_add(self.contributions[source], Ordering::Relaxed);
+            true
         }
     }
 
-    impl BFS {
-        fn new(node_count: usize) -> Self {
-            let mut parents = Vec::with_capacity(node_count);
-            parents.resize_with(node_count, || AtomicUsize::new(usize::MAX));
+    impl MapperFlags for Propagate<'_> {
+        fn update_always_returns_true(&self) -> bool {
+            true
+        }
 
-            Self { parents }
+        fn check_always_returns_true(&self) -> bool {
+            true
         }
     }
 
-    pub(crate) fn bfs<G: Graph + Sync>(graph: G, root: usize) -> Vec<AtomicUsize> {
-        let mut bfs = BFS::new(graph.node_count());
-        bfs.parents[root] = AtomicUsize::new(root);
+    pub(crate) fn page_rank<G: Graph + Sync>(
+        graph: &G,
+        config: &PageRankConfig,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<f64>, RunStats) {
+        let node_count = graph.node_count();
+        let damping = config.damping;
+        let mut ranks = vec![1.0 / node_count as f64; node_count];
+        let mut contributions = vec![0.0; node_count];
+        let incoming = par_vec_with(node_count, AtomicF64::default);
 
-        let mut frontier = ligra::NodeSubset::single(graph.node_count(), root);
-        while frontier.subset_count() != 0 {
-            ligra::relationship_map(&graph, &mut frontier, &bfs);
+        let mut running = control.start();
+        for _ in 0..config.max_iterations {
+            if !running.next_iteration(node_count) {
+                break;
+            }
+
+            // nodes without relationships hand their rank to every node alike
+            let dangling: f64 = contributions
+                .par_iter_mut()
+                .zip(ranks.par_iter())
+                .enumerate()
+                .map(
+                    |(node, (contribution, &rank))| match graph.out_degree(node) {
+                        0 => {
+                            *contribution = 0.0;
+                            rank
+                        }
+                        degree => {
+                            *contribution = rank / degree as f64;
+                            0.0
+                        }
+                    },
+                )
+                .sum();
+
+            let mut all_nodes = NodeSubset::full(node_count);
+            let propagate = Propagate {
+                contributions: &contributions,
+                incoming: &incoming,
+            };
+            ligra::relationship_map_with(graph, &mut all_nodes, &propagate, workspace);
+            workspace.recycle(all_nodes);
+
+            let base = (1.0 - damping + damping * dangling) / node_count as f64;
+            let error: f64 = ranks
+                .par_iter_mut()
+                .zip(incoming.par_iter())
+                .map(|(rank, incoming)| {
+                    let next = base + damping * incoming.swap(0.0, Ordering::Relaxed);
+                    let change = (next - *rank).abs();
+                    *rank = next;
+                    change
+                })
+                .sum();
+
+            if error < config.tolerance {
+                break;
+            }
         }
 
-        bfs.parents
+        (ranks, running.finish())
     }
 }
 
 mod pagerank_delta {
+    use super::{PageRankDeltaConfig, RunControl, RunStats};
     use crate::{
         graph::Graph,
-        ligra::{self, par_vec_with, NodeMapper, RelationshipMapper},
+        ligra::{self, par_vec_with, MapperFlags, NodeMapper, RelationshipMapper, Workspace},
     };
     use atomic_float::AtomicF64;
     use ligra::NodeSubset;
+    use rayon::prelude::*;
     use std::sync::atomic::Ordering;
 
-    const DAMPING_FACTOR: f64 = 0.85;
-    const TOLERANCE: f64 = 1E-7;
     const DELTA_THRESHOLD: f64 = 1E-2;
-    const ALPHA: f64 = 1.0 - DAMPING_FACTOR;
 
     struct PageRankDelta<'g, G> {
         graph: &'g G,
         deltas: Vec<AtomicF64>,
         neighbors_rank: Vec<AtomicF64>,
         page_rank: Vec<AtomicF64>,
+        /// Deltas that were too small to be propagated yet
+        pending: Vec<AtomicF64>,
         one_over_n: f64,
         sum_of_delta: AtomicF64,
+        /// The deltas of the nodes without relationships in the next frontier
+        dangling: AtomicF64,
+        /// What every node receives from the dangling deltas of the current frontier
+        dangling_share: f64,
+        damping: f64,
     }
 
     struct FirstRound<'a, 'g, G>(&'a PageRankDelta<'g, G>);
 
-    impl<'a, 'g, G> NodeMapper for FirstRound<'a, 'g, G> {
+    impl<'a, 'g, G: Graph> NodeMapper for FirstRound<'a, 'g, G> {
         fn update(&self, node: usize) -> bool {
-            // TODO ALPHA / node_count for normalization
-            let mut delta =
-                self.0.neighbors_rank[node].swap(0.0, Ordering::SeqCst) * DAMPING_FACTOR + ALPHA;
-            let current_rank = self.0.page_rank[node].fetch_add(delta, Ordering::SeqCst) + delta;
-            delta -= self.0.one_over_n;
-            self.0.deltas[node].store(delta, Ordering::SeqCst);
-            self.0.sum_of_delta.fetch_add(delta, Ordering::SeqCst);
-            delta.abs() > (current_rank + DELTA_THRESHOLD)
+            let pr = self.0;
+            let rank = pr.neighbors_rank[node].swap(0.0, Ordering::Relaxed) * pr.damping
+                + pr.dangling_share
+                + (1.0 - pr.damping) * pr.one_over_n;
+            pr.page_rank[node].store(rank, Ordering::Relaxed);
+            // the initial ranks of `one_over_n` were already propagated as deltas
+            let delta = rank - pr.one_over_n;
+            pr.sum_of_delta.fetch_add(delta.abs(), Ordering::Relaxed);
+            pr.record(node, delta, rank)
         }
     }
 
-    impl<'g, G> NodeMapper for PageRankDelta<'g, G> {
+    impl<'g, G: Graph> NodeMapper for PageRankDelta<'g, G> {
         fn update(&self, node: usize) -> bool {
-            let delta = self.neighbors_rank[node].swap(0.0, Ordering::SeqCst) * DAMPING_FACTOR;
-            self.deltas[node].store(delta, Ordering::SeqCst);
-            self.sum_of_delta.fetch_add(delta, Ordering::SeqCst);
-
-            let current_rank = self.page_rank[node].load(Ordering::SeqCst);
-
-            if current_rank.abs() > (current_rank * DELTA_THRESHOLD) {
-                self.page_rank[node].store(current_rank + delta, Ordering::SeqCst);
-                true
-            } else {
-                false
-            }
+            let change = self.neighbors_rank[node].swap(0.0, Ordering::Relaxed) * self.damping
+                + self.dangling_share;
+            let rank = self.page_rank[node].fetch_add(change, Ordering::Relaxed) + change;
+            // held back deltas were already counted in the round they were applied
+            self.sum_of_delta.fetch_add(change.abs(), Ordering::Relaxed);
+            let delta = self.pending[node].swap(0.0, Ordering::Relaxed) + change;
+            self.record(node, delta, rank)
         }
     }
 
     impl<'g, G: Graph> RelationshipMapper for PageRankDelta<'g, G> {
         fn update(&self, source: usize, target: usize) -> bool {
             let delta =
-                self.deltas[source].load(Ordering::SeqCst) / self.graph.out_degree(source) as f64;
-            let rank = self.neighbors_rank[target].fetch_add(delta, Ordering::SeqCst);
+                self.deltas[source].load(Ordering::Relaxed) / self.graph.out_degree(source) as f64;
+            let rank = self.neighbors_rank[target].fetch_add(delta, Ordering::Relaxed);
 
             rank == 0.0
         }
+    }
 
+    impl<'g, G: Graph> MapperFlags for PageRankDelta<'g, G> {
         fn check_always_returns_true(&self) -> bool {
             true
         }
@@ -239,8 +2069,8 @@ mod pagerank_delta {
         }
     }
 
-    impl<'g, G: Graph> PageRankDelta<'g, G> {
-        fn new(graph: &'g G) -> Self {
+    impl<'g, G: Graph + Sync> PageRankDelta<'g, G> {
+        fn new(graph: &'g G, damping: f64) -> Self {
             let node_count = graph.node_count();
             let initial_value = 1.0 / node_count as f64;
 
@@ -249,47 +2079,790 @@ mod pagerank_delta {
             let page_rank = par_vec_with(node_count, AtomicF64::default);
 
             let one_over_n = 1.0 / node_count as f64;
+            let sinks = (0..node_count)
+                .into_par_iter()
+                .filter(|&node| graph.out_degree(node) == 0)
+                .count();
 
             PageRankDelta {
                 graph,
                 deltas,
                 neighbors_rank,
                 page_rank,
+                pending: par_vec_with(node_count, AtomicF64::default),
                 one_over_n,
                 sum_of_delta: AtomicF64::default(),
+                dangling: AtomicF64::new(sinks as f64 * initial_value),
+                dangling_share: 0.0,
+                damping,
+            }
+        }
+    }
+
+    impl<'g, G: Graph> PageRankDelta<'g, G> {
+        /// Stores the `delta` of `node` and returns whether it is large enough
+        /// compared to `rank` to be propagated in the next round.
+        fn record(&self, node: usize, delta: f64, rank: f64) -> bool {
+            self.deltas[node].store(delta, Ordering::Relaxed);
+
+            let significant = delta.abs() > rank * DELTA_THRESHOLD;
+            if !significant {
+                self.pending[node].store(delta, Ordering::Relaxed);
+            } else if self.graph.out_degree(node) == 0 {
+                self.dangling.fetch_add(delta, Ordering::Relaxed);
             }
+            significant
+        }
+
+        /// Spreads the deltas of the dangling nodes in the frontier over all nodes,
+        /// like the rank of nodes without relationships in [`super::page_rank`].
+        fn spread_dangling(&mut self) {
+            let dangling = std::mem::take(self.dangling.get_mut());
+            self.dangling_share = dangling * self.damping * self.one_over_n;
         }
 
         fn sum_of_delta_and_reset(&self) -> f64 {
-            self.sum_of_delta.swap(0.0, Ordering::SeqCst)
+            self.sum_of_delta.swap(0.0, Ordering::Relaxed)
         }
     }
 
     pub(crate) fn page_rank_delta<G: Graph + Sync>(
-        graph: G,
-        mut max_iterations: usize,
-    ) -> Vec<AtomicF64> {
-        let pr = PageRankDelta::new(&graph);
+        graph: &G,
+        config: &PageRankDeltaConfig,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> (Vec<AtomicF64>, RunStats) {
+        let mut pr = PageRankDelta::new(graph, config.damping);
+        let mut max_iterations = config.max_iterations;
 
         let all_nodes = NodeSubset::full(graph.node_count());
         let mut frontier = NodeSubset::full(graph.node_count());
+        let mut running = control.start();
+
+        if !running.next_iteration(frontier.subset_count()) {
+            return (pr.page_rank, running.finish());
+        }
 
-        ligra::relationship_map(&graph, &mut frontier, &pr);
-        frontier = ligra::node_filter(&all_nodes, &FirstRound(&pr));
+        ligra::relationship_map_with(graph, &mut frontier, &pr, workspace);
+        pr.spread_dangling();
+        let next = ligra::node_filter_with(&all_nodes, &FirstRound(&pr), workspace);
+        workspace.recycle(std::mem::replace(&mut frontier, next));
 
         // remaining iterations
         loop {
             let error = pr.sum_of_delta_and_reset();
-            max_iterations -= 1;
+            max_iterations = max_iterations.saturating_sub(1);
 
-            if error < TOLERANCE || max_iterations == 0 {
+            if error < config.tolerance
+                || frontier.is_empty()
+                || max_iterations == 0
+                || !running.next_iteration(frontier.subset_count())
+            {
                 break;
             }
 
-            ligra::relationship_map(&graph, &mut frontier, &pr);
-            frontier = ligra::node_filter(&all_nodes, &pr);
+            ligra::relationship_map_with(graph, &mut frontier, &pr, workspace);
+            pr.spread_dangling();
+            let next = ligra::node_filter_with(&all_nodes, &pr, workspace);
+            workspace.recycle(std::mem::replace(&mut frontier, next));
+        }
+
+        (pr.page_rank, running.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::MockGraph;
+
+    /// Two undirected components {0, 1, 2} and {3, 4} and the isolated node 5.
+    fn two_components() -> MockGraph {
+        MockGraph::new(vec![vec![1], vec![0, 2], vec![1], vec![4], vec![3], vec![]])
+    }
+
+    /// An undirected path, long enough for sparse traversals.
+    fn path(n: usize) -> MockGraph {
+        MockGraph::new(
+            (0..n)
+                .map(|i| {
+                    (i.saturating_sub(1)..=(i + 1).min(n - 1))
+                        .filter(|&j| j != i)
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// The relationships `rels` between `nodes` nodes, with ids taken modulo `nodes`.
+    /// A symmetric graph also has the reverse of every relationship.
+    fn random_graph(rels: &[(u8, u8)], nodes: usize, symmetric: bool) -> MockGraph {
+        MockGraph::new(random_lists(rels, nodes, symmetric))
+    }
+
+    /// Like [`random_graph`] for a symmetric graph, but without self loops
+    /// and parallel relationships.
+    fn simple_graph(rels: &[(u8, u8)], nodes: usize) -> MockGraph {
+        let mut out = random_lists(rels, nodes, true);
+        for (source, targets) in out.iter_mut().enumerate() {
+            targets.retain(|&target| target != source);
+            targets.sort_unstable();
+            targets.dedup();
+        }
+        MockGraph::new(out)
+    }
+
+    /// Like [`random_graph`] for a directed graph, with the weight of every relationship.
+    fn random_weighted_graph(rels: &[(u8, u8, Weight)], nodes: usize) -> MockGraph {
+        let mut out = vec![Vec::new(); nodes];
+        for &(source, target, weight) in rels {
+            out[usize::from(source) % nodes].push((usize::from(target) % nodes, weight));
+        }
+        MockGraph::weighted(out)
+    }
+
+    fn random_lists(rels: &[(u8, u8)], nodes: usize, symmetric: bool) -> Vec<Vec<usize>> {
+        let mut out = vec![Vec::new(); nodes];
+        for &(source, target) in rels {
+            let (source, target) = (usize::from(source) % nodes, usize::from(target) % nodes);
+            out[source].push(target);
+            if symmetric {
+                out[target].push(source);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let result = connected_components(&two_components(), &CcConfig::default());
+        assert_eq!(result.values, vec![0, 0, 0, 3, 3, 5]);
+        assert_eq!(result.stats.stopped, None);
+    }
+
+    #[test]
+    fn test_bfs() {
+        let result = bfs(&two_components(), &BfsConfig::new(2));
+        assert_eq!(
+            result.values,
+            vec![1, 2, 2, usize::MAX, usize::MAX, usize::MAX]
+        );
+        assert_eq!(result.stats.iterations, 3);
+    }
+
+    #[test]
+    fn test_bfs_with_max_iterations() {
+        let control = RunControl::new().with_max_iterations(1);
+        let result = bfs_with(
+            &two_components(),
+            &BfsConfig::new(2),
+            &mut Workspace::new(),
+            &control,
+        );
+        assert_eq!(result.values[..3], [usize::MAX, 2, 2]);
+        assert_eq!(result.stats.stopped, Some(Stop::MaxIterations));
+    }
+
+    #[test]
+    #[should_panic(expected = "source 6 is not a node")]
+    fn test_bfs_source_out_of_range() {
+        bfs(&two_components(), &BfsConfig::new(6));
+    }
+
+    #[test]
+    fn test_bfs_tree() {
+        let tree = bfs_tree(&two_components(), &BfsConfig::new(0));
+        assert_eq!(
+            tree.distances,
+            vec![0, 1, 2, usize::MAX, usize::MAX, usize::MAX]
+        );
+        assert_eq!(tree.sources[..3], [0, 0, 0]);
+        assert_eq!(tree.edges().collect::<Vec<_>>(), vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_multi_source_bfs() {
+        let n = 100;
+        let graph = path(n);
+        let tree = bfs_tree(&graph, &BfsConfig::from_sources(vec![0, n - 1, 0]));
+        for node in 0..n {
+            assert_eq!(tree.distances[node], node.min(n - 1 - node));
+            assert_eq!(tree.sources[node], if node < n / 2 { 0 } else { n - 1 });
+        }
+        assert_eq!(tree.edges().count(), n - 2);
+    }
+
+    /// Sequential Brandes over all sources, following outgoing relationships.
+    fn brandes<G: Graph>(graph: &G) -> Vec<f64> {
+        let n = graph.node_count();
+        let mut scores = vec![0.0; n];
+        for source in 0..n {
+            let mut distances = vec![usize::MAX; n];
+            let mut paths = vec![0.0; n];
+            let mut predecessors = vec![Vec::new(); n];
+            let mut order = Vec::new();
+            let mut queue = std::collections::VecDeque::from(vec![source]);
+            distances[source] = 0;
+            paths[source] = 1.0;
+            while let Some(node) = queue.pop_front() {
+                order.push(node);
+                for &next in graph.out(node) {
+                    if distances[next] == usize::MAX {
+                        distances[next] = distances[node] + 1;
+                        queue.push_back(next);
+                    }
+                    if distances[next] == distances[node] + 1 {
+                        paths[next] += paths[node];
+                        predecessors[next].push(node);
+                    }
+                }
+            }
+            let mut dependencies = vec![0.0; n];
+            for &node in order.iter().rev() {
+                for &predecessor in predecessors[node].iter() {
+                    dependencies[predecessor] +=
+                        paths[predecessor] / paths[node] * (1.0 + dependencies[node]);
+                }
+                if node != source {
+                    scores[node] += dependencies[node];
+                }
+            }
+        }
+        scores
+    }
+
+    fn all_close(actual: &[f64], expected: &[f64]) -> bool {
+        actual.len() == expected.len()
+            && actual
+                .iter()
+                .zip(expected)
+                .all(|(a, e)| (a - e).abs() < 1e-9)
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert!(
+            all_close(actual, expected),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_betweenness_centrality() {
+        // a directed diamond, both paths from 0 to 3 are shortest paths
+        let graph = MockGraph::new(vec![vec![1, 2], vec![3], vec![3], vec![]]);
+        let result = betweenness_centrality(&graph, &BcConfig::default());
+        assert_close(&result.values, &[0.0, 0.5, 0.5, 0.0]);
+
+        let graph = path(100);
+        let result = betweenness_centrality(&graph, &BcConfig::default());
+        assert_close(&result.values, &brandes(&graph));
+    }
+
+    #[quickcheck]
+    fn test_betweenness_centrality_matches_brandes(rels: Vec<(u8, u8)>) -> bool {
+        let graph = random_graph(&rels, 16, false);
+        let result = betweenness_centrality(&graph, &BcConfig::default());
+        all_close(&result.values, &brandes(&graph))
+    }
+
+    #[test]
+    fn test_betweenness_centrality_sources() {
+        let graph = path(5);
+        let result = betweenness_centrality(&graph, &BcConfig::from_sources(vec![0, 0]));
+        assert_close(&result.values, &[0.0, 3.0, 2.0, 1.0, 0.0]);
+
+        // sampling every node is exact
+        let exact = betweenness_centrality(&graph, &BcConfig::default());
+        let sampled = betweenness_centrality(&graph, &BcConfig::sampled(5).with_seed(42));
+        assert_close(&sampled.values, &exact.values);
+    }
+
+    #[test]
+    fn test_betweenness_centrality_sampled_and_stopped() {
+        // the search from source 0 takes 5 iterations, the one from source 1 is stopped
+        let control = RunControl::new().with_max_iterations(5);
+        let config = BcConfig::sampled(5).with_seed(42);
+        let result =
+            betweenness_centrality_with(&path(5), &config, &mut Workspace::new(), &control);
+        assert_eq!(result.stats.stopped, Some(Stop::MaxIterations));
+        assert_close(&result.values, &[0.0, 15.0, 10.0, 5.0, 0.0]);
+    }
+
+    #[test]
+    fn test_sample_nodes() {
+        let sample = sample_nodes(1000, 10, 42);
+        assert_eq!(sample.len(), 10);
+        assert!(sample.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(sample, sample_nodes(1000, 10, 42));
+        assert_ne!(sample, sample_nodes(1000, 10, 7));
+        assert_eq!(sample_nodes(3, 10, 42), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_radii() {
+        // with every node as a source, the estimates are exact
+        let estimate = radii(&path(10), &RadiiConfig::default());
+        assert_eq!(estimate.eccentricities, vec![9, 8, 7, 6, 5, 5, 6, 7, 8, 9]);
+        assert_eq!(estimate.diameter, 9);
+
+        let estimate = radii(&two_components(), &RadiiConfig::default());
+        assert_eq!(estimate.eccentricities, vec![2, 1, 2, 1, 1, 0]);
+        assert_eq!(estimate.diameter, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "radii needs between 1 and 64 samples, got 0")]
+    fn test_radii_without_samples() {
+        radii(&path(10), &RadiiConfig::default().with_samples(0));
+    }
+
+    #[quickcheck]
+    fn test_radii_matches_bfs(rels: Vec<(u8, u8)>, samples: u8, seed: u64) -> bool {
+        let graph = random_graph(&rels, 16, false);
+        let config = RadiiConfig::default()
+            .with_samples(usize::from(samples % 16) + 1)
+            .with_seed(seed);
+
+        let mut expected = vec![usize::MAX; 16];
+        for source in sample_nodes(16, config.samples, seed) {
+            let tree = bfs_tree(&graph, &BfsConfig::new(source));
+            for (node, &distance) in tree.distances.iter().enumerate() {
+                if distance != usize::MAX {
+                    expected[node] = if expected[node] == usize::MAX {
+                        distance
+                    } else {
+                        expected[node].max(distance)
+                    };
+                }
+            }
+        }
+
+        radii(&graph, &config).eccentricities == expected
+    }
+
+    /// Sequential peeling, always removing a node of smallest remaining degree.
+    fn peel(graph: &MockGraph) -> Vec<usize> {
+        let n = graph.node_count();
+        let mut degrees = (0..n)
+            .map(|node| graph.out_degree(node))
+            .collect::<Vec<_>>();
+        let mut coreness = vec![usize::MAX; n];
+        let mut k = 0;
+        for _ in 0..n {
+            let node = (0..n)
+                .filter(|&node| coreness[node] == usize::MAX)
+                .min_by_key(|&node| degrees[node])
+                .unwrap();
+            k = k.max(degrees[node]);
+            coreness[node] = k;
+            for &target in graph.out(node) {
+                degrees[target] = degrees[target].saturating_sub(1);
+            }
+        }
+        coreness
+    }
+
+    #[test]
+    fn test_k_core() {
+        // a triangle {0, 1, 2} with the pendant 3 and the isolated node 4
+        let graph = MockGraph::new(vec![vec![1, 2, 3], vec![0, 2], vec![0, 1], vec![0], vec![]]);
+        let kcore = k_core(&graph, &KCoreConfig::default());
+        assert_eq!(kcore.coreness, vec![2, 2, 2, 1, 0]);
+        assert_eq!(kcore.degeneracy, 2);
+
+        let kcore = k_core(&path(100), &KCoreConfig::default());
+        assert!(kcore.coreness.iter().all(|&k| k == 1));
+        assert_eq!(kcore.degeneracy, 1);
+    }
+
+    #[quickcheck]
+    fn test_k_core_matches_peeling(rels: Vec<(u8, u8)>) -> bool {
+        let graph = simple_graph(&rels, 16);
+
+        let kcore = k_core(&graph, &KCoreConfig::default());
+        kcore.coreness == peel(&graph)
+    }
+
+    #[test]
+    fn test_k_core_subgraph() {
+        let graph = MockGraph::new(vec![vec![1, 2, 3], vec![0, 2], vec![0, 1], vec![0], vec![]]);
+        let kcore = k_core(&graph, &KCoreConfig::default());
+
+        let core = kcore.subgraph(&graph, 2);
+        assert_eq!(core.node_count(), 5);
+        assert_eq!(core.rel_count(), 6);
+        assert_eq!(core.out(0), &[1, 2]);
+        assert_eq!(core.out(3), &[] as &[usize]);
+
+        assert_eq!(kcore.subgraph(&graph, 3).rel_count(), 0);
+    }
+
+    #[quickcheck]
+    fn test_filtered_graph_matches_subgraph(rels: Vec<(u8, u8)>) -> bool {
+        // both filters keep the graph symmetric
+        let filtered = FilteredGraph::new(simple_graph(&rels, 16))
+            .with_node_filter(|node| node % 3 != 0)
+            .with_relationship_filter(|source, target| (source + target) % 5 != 0);
+        let subgraph = AdjacencyGraph::from_graph(&filtered);
+
+        let config = KCoreConfig::default();
+        let same_coreness =
+            k_core(&filtered, &config).coreness == k_core(&subgraph, &config).coreness;
+
+        let config = PageRankConfig::default().with_max_iterations(20);
+        let same_ranks = all_close(
+            &page_rank(&filtered, &config).values,
+            &page_rank(&subgraph, &config).values,
+        );
+
+        same_coreness && same_ranks
+    }
+
+    #[test]
+    fn test_maximal_independent_set() {
+        let graph = path(100);
+        let config = MisConfig::default().with_seed(42);
+        let result = maximal_independent_set(&graph, &config);
+        verify_independent_set(&graph, &result.values).unwrap();
+        assert_eq!(
+            result.values,
+            maximal_independent_set(&graph, &config).values
+        );
+
+        // isolated nodes are always in the set
+        let result = maximal_independent_set(&two_components(), &MisConfig::default());
+        verify_independent_set(&two_components(), &result.values).unwrap();
+        assert!(result.values[5]);
+    }
+
+    #[quickcheck]
+    fn test_maximal_independent_set_is_valid(rels: Vec<(u8, u8)>, seed: u64) -> bool {
+        let graph = random_graph(&rels, 16, true);
+
+        let config = MisConfig::default().with_seed(seed);
+        let result = maximal_independent_set(&graph, &config);
+        verify_independent_set(&graph, &result.values).is_ok()
+    }
+
+    #[test]
+    fn test_verify_independent_set() {
+        let graph = two_components();
+        verify_independent_set(&graph, &[true, false, true, true, false, true]).unwrap();
+
+        let err = verify_independent_set(&graph, &[true, true, false, true, false, true]);
+        assert!(err.unwrap_err().to_string().contains("neighbors"));
+        let err = verify_independent_set(&graph, &[false, true, false, true, false, false]);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "node 5 could be added to the set, it has no neighbor in it"
+        );
+        assert!(verify_independent_set(&graph, &[true]).is_err());
+    }
+
+    /// Sequential Bellman-Ford, `None` if there is a negative cycle reachable from `source`.
+    fn shortest_distances(graph: &MockGraph, source: usize) -> Option<Vec<Weight>> {
+        let n = graph.node_count();
+        let mut distances = vec![Weight::MAX; n];
+        distances[source] = 0;
+        for _ in 0..=n {
+            let mut changed = false;
+            for node in 0..n {
+                if distances[node] == Weight::MAX {
+                    continue;
+                }
+                for (&target, &weight) in graph.out(node).iter().zip(graph.out_weights(node)) {
+                    if distances[node] + weight < distances[target] {
+                        distances[target] = distances[node] + weight;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return Some(distances);
+            }
+        }
+        None
+    }
+
+    /// Checks that the predecessors form a tree of shortest paths rooted in `source`.
+    fn is_shortest_path_tree(graph: &MockGraph, paths: &ShortestPaths, source: usize) -> bool {
+        (0..graph.node_count()).all(|node| {
+            let predecessor = paths.predecessors[node];
+            if node == source || paths.distances[node] == Weight::MAX {
+                return predecessor == usize::MAX;
+            }
+            let tight = graph
+                .out(predecessor)
+                .iter()
+                .zip(graph.out_weights(predecessor))
+                .any(|(&target, &weight)| {
+                    target == node && paths.distances[predecessor] + weight == paths.distances[node]
+                });
+            let mut ancestor = node;
+            let mut steps = 0;
+            while ancestor != source && steps < graph.node_count() {
+                ancestor = paths.predecessors[ancestor];
+                steps += 1;
+            }
+            tight && ancestor == source
+        })
+    }
+
+    #[test]
+    fn test_bellman_ford() {
+        // 0 -(4)-> 1 -(-2)-> 2 -(1)-> 3, 0 -(5)-> 2, the zero length cycle over 3 -(1)-> 1
+        // and the unreachable node 4
+        let graph = MockGraph::weighted(vec![
+            vec![(1, 4), (2, 5)],
+            vec![(2, -2)],
+            vec![(3, 1)],
+            vec![(1, 1)],
+            vec![(0, 1)],
+        ]);
+        let paths = bellman_ford(&graph, &BellmanFordConfig::new(0));
+        assert!(!paths.negative_cycle);
+        assert_eq!(paths.distances, vec![0, 4, 2, 3, Weight::MAX]);
+        assert_eq!(paths.predecessors, vec![usize::MAX, 0, 1, 2, usize::MAX]);
+    }
+
+    #[test]
+    fn test_bellman_ford_negative_cycle() {
+        // the cycle 1 -> 2 -> 1 has a length of -1
+        let graph = MockGraph::weighted(vec![vec![(1, 1)], vec![(2, 2)], vec![(1, -3)], vec![]]);
+        let paths = bellman_ford(&graph, &BellmanFordConfig::new(0));
+        assert!(paths.negative_cycle);
+        assert!(paths.predecessors.iter().all(|&p| p == usize::MAX));
+
+        // the cycle is not reachable from 3
+        let paths = bellman_ford(&graph, &BellmanFordConfig::new(3));
+        assert!(!paths.negative_cycle);
+        assert_eq!(paths.distances[3], 0);
+    }
+
+    #[quickcheck]
+    fn test_bellman_ford_matches_sequential(rels: Vec<(u8, u8, i8)>, source: u8) -> bool {
+        // mostly positive weights, so that not every graph has a negative cycle
+        let rels = rels
+            .into_iter()
+            .map(|(source, target, weight)| (source, target, Weight::from(weight % 16) + 4))
+            .collect::<Vec<_>>();
+        let graph = random_weighted_graph(&rels, 16);
+        let source = usize::from(source % 16);
+
+        let paths = bellman_ford(&graph, &BellmanFordConfig::new(source));
+        match shortest_distances(&graph, source) {
+            Some(distances) => {
+                !paths.negative_cycle
+                    && paths.distances == distances
+                    && is_shortest_path_tree(&graph, &paths, source)
+            }
+            None => paths.negative_cycle,
+        }
+    }
+
+    /// Sequential Dijkstra, for non-negative weights only.
+    fn dijkstra(graph: &MockGraph, source: usize) -> Vec<Weight> {
+        use std::{cmp::Reverse, collections::BinaryHeap};
+
+        let mut distances = vec![Weight::MAX; graph.node_count()];
+        let mut queue = BinaryHeap::new();
+        distances[source] = 0;
+        queue.push(Reverse((0, source)));
+        while let Some(Reverse((distance, node))) = queue.pop() {
+            if distance > distances[node] {
+                continue;
+            }
+            for (&target, &weight) in graph.out(node).iter().zip(graph.out_weights(node)) {
+                if distance + weight < distances[target] {
+                    distances[target] = distance + weight;
+                    queue.push(Reverse((distance + weight, target)));
+                }
+            }
+        }
+        distances
+    }
+
+    #[test]
+    fn test_delta_stepping() {
+        // 0 -(4)-> 1 -(1)-> 2, 0 -(7)-> 2 -(0)-> 3 and the unreachable node 4
+        let graph = MockGraph::weighted(vec![
+            vec![(1, 4), (2, 7)],
+            vec![(2, 1)],
+            vec![(3, 0)],
+            vec![],
+            vec![(0, 1)],
+        ]);
+        for delta in [1, 3, 100] {
+            let config = DeltaSteppingConfig::new(0).with_delta(delta);
+            let paths = delta_stepping(&graph, &config);
+            assert_eq!(paths.distances, vec![0, 4, 5, 5, Weight::MAX]);
+            assert_eq!(paths.predecessors, vec![usize::MAX, 0, 1, 2, usize::MAX]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "node 1 has a relationship of weight -2")]
+    fn test_delta_stepping_negative_weight() {
+        let graph = MockGraph::weighted(vec![vec![(1, 3)], vec![(2, -2)], vec![]]);
+        delta_stepping(&graph, &DeltaSteppingConfig::new(0));
+    }
+
+    #[quickcheck]
+    fn test_delta_stepping_matches_dijkstra(
+        rels: Vec<(u8, u8, u8)>,
+        source: u8,
+        delta: u8,
+    ) -> bool {
+        let rels = rels
+            .into_iter()
+            .map(|(source, target, weight)| (source, target, Weight::from(weight % 32)))
+            .collect::<Vec<_>>();
+        let graph = random_weighted_graph(&rels, 16);
+        let source = usize::from(source % 16);
+
+        let config = DeltaSteppingConfig::new(source).with_delta(Weight::from(delta % 16) + 1);
+        let paths = delta_stepping(&graph, &config);
+        paths.distances == dijkstra(&graph, source) && is_shortest_path_tree(&graph, &paths, source)
+    }
+
+    #[test]
+    fn test_triangle_count() {
+        // the complete graph {0, 1, 2, 3} with the pendant 4
+        let graph = MockGraph::new(vec![
+            vec![1, 2, 3, 4],
+            vec![0, 2, 3],
+            vec![0, 1, 3],
+            vec![0, 1, 2],
+            vec![0],
+        ]);
+        let config = TriangleConfig::default().with_node_counts(true);
+        let triangles = triangle_count(&graph, &config);
+        assert_eq!(triangles.count, 4);
+        assert_eq!(triangles.node_counts, Some(vec![3, 3, 3, 3, 0]));
+
+        let triangles = triangle_count(&path(100), &TriangleConfig::default());
+        assert_eq!(triangles.count, 0);
+        assert_eq!(triangles.node_counts, None);
+    }
+
+    #[quickcheck]
+    fn test_triangle_count_matches_brute_force(rels: Vec<(u8, u8)>) -> bool {
+        // parallel relationships and self loops are ignored
+        let graph = random_graph(&rels, 16, true);
+        let mut adjacent = [[false; 16]; 16];
+        for &(source, target) in &rels {
+            let (source, target) = (usize::from(source % 16), usize::from(target % 16));
+            adjacent[source][target] = source != target;
+            adjacent[target][source] = source != target;
+        }
+
+        let mut expected = vec![0; 16];
+        let mut count = 0;
+        for a in 0..16 {
+            for b in a + 1..16 {
+                for c in b + 1..16 {
+                    if adjacent[a][b] && adjacent[b][c] && adjacent[a][c] {
+                        count += 1;
+                        expected[a] += 1;
+                        expected[b] += 1;
+                        expected[c] += 1;
+                    }
+                }
+            }
         }
 
-        pr.page_rank
+        let config = TriangleConfig::default().with_node_counts(true);
+        let triangles = triangle_count(&graph, &config);
+        triangles.count == count && triangles.node_counts == Some(expected)
+    }
+
+    #[test]
+    fn test_page_rank() {
+        // a directed cycle, where every node ends up with the same rank
+        let graph = MockGraph::new(vec![vec![1], vec![2], vec![0]]);
+        let result = page_rank(&graph, &PageRankConfig::default());
+        assert_close(&result.values, &[1.0 / 3.0; 3]);
+        assert_eq!(result.stats.iterations, 1);
+
+        // 0 -> 1 with the dangling node 1, whose rank flows back to both nodes
+        let graph = MockGraph::new(vec![vec![1], vec![]]);
+        let config = PageRankConfig::default().with_tolerance(1E-12);
+        let result = page_rank(&graph, &config);
+        assert_close(&result.values, &[1.0 / 2.85, 1.85 / 2.85]);
+
+        let config = PageRankConfig::default().with_damping(0.0);
+        assert_close(&page_rank(&graph, &config).values, &[0.5, 0.5]);
+    }
+
+    #[quickcheck]
+    fn test_page_rank_sums_to_one(rels: Vec<(u8, u8)>) -> bool {
+        let graph = random_graph(&rels, 16, false);
+
+        let config = PageRankConfig::default().with_max_iterations(20);
+        let result = page_rank(&graph, &config);
+        (result.values.iter().sum::<f64>() - 1.0).abs() < 1e-9
+            && result.values.iter().all(|&rank| rank > 0.0)
+    }
+
+    #[test]
+    fn test_page_rank_delta() {
+        // a directed cycle, where every node ends up with the same rank
+        let graph = MockGraph::new(vec![vec![1], vec![2], vec![0]]);
+        let config = PageRankDeltaConfig::default().with_max_iterations(10);
+        let result = page_rank_delta(&graph, &config);
+        assert_eq!(result.values.len(), 3);
+        assert!(result.values.iter().all(|&rank| rank == result.values[0]));
+        assert!(result.values[0] > 0.0);
+
+        // 0 -> 1 with the dangling node 1, like in `test_page_rank`
+        let graph = MockGraph::new(vec![vec![1], vec![]]);
+        let config = PageRankDeltaConfig::default().with_tolerance(1E-12);
+        let result = page_rank_delta(&graph, &config);
+        let expected = [1.0 / 2.85, 1.85 / 2.85];
+        assert!(
+            result
+                .values
+                .iter()
+                .zip(expected)
+                .all(|(rank, e)| (rank - e).abs() < 1e-2 * e),
+            "{:?} != {:?}",
+            result.values,
+            expected
+        );
+        assert!(result.stats.iterations < config.max_iterations);
+    }
+
+    #[test]
+    fn test_page_rank_delta_tolerance() {
+        // every node links to three pseudo-random nodes, whose deltas stay significant
+        // for 10 rounds, but sum up to less than the tolerance after 7
+        let graph = MockGraph::new(
+            (0..64)
+                .map(|i: usize| vec![(i * 7 + 1) % 64, (i * i + 3) % 64, i / 2])
+                .collect(),
+        );
+        let config = PageRankDeltaConfig::default()
+            .with_tolerance(1E-2)
+            .with_max_iterations(8);
+        let result = page_rank_delta(&graph, &config);
+        assert!(result.stats.iterations < config.max_iterations);
+    }
+
+    #[quickcheck]
+    fn test_page_rank_delta_sums_to_one(rels: Vec<(u8, u8)>) -> bool {
+        // the last nodes have no relationships
+        let rels = rels
+            .into_iter()
+            .map(|(source, target)| (source % 12, target))
+            .collect::<Vec<_>>();
+        let graph = random_graph(&rels, 16, false);
+
+        // deltas below 1% of the rank are not propagated,
+        // which moves the sum by at most `0.01 * damping / (1 - damping)`
+        let result = page_rank_delta(&graph, &PageRankDeltaConfig::default());
+        (result.values.iter().sum::<f64>() - 1.0).abs() < 0.06
+            && result.values.iter().all(|&rank| rank > 0.0)
     }
 }
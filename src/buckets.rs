@@ -0,0 +1,220 @@
+use super::{
+    node_filter_with, par_vec,
+    primitives::{filter, histogram},
+    NodeMapper, NodeSubset, Workspace,
+};
+use rayon::prelude::*;
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Groups nodes by an integer priority and hands them out bucket by bucket,
+/// in increasing order of the priority (the bucketing of Julienne).
+///
+/// Moving a node to a different bucket does not remove it from its previous one.
+/// Such outdated entries are filtered out when their bucket is taken.
+#[derive(Debug)]
+pub struct Buckets {
+    /// The bucket every node is currently in, [`Buckets::NONE`] if it is in none
+    current: Vec<AtomicUsize>,
+    /// Nodes by bucket, including outdated entries
+    buckets: BTreeMap<usize, Vec<usize>>,
+}
+
+/// Keeps the nodes that are still in the bucket that is being taken.
+struct InBucket<'a> {
+    current: &'a [AtomicUsize],
+    bucket: usize,
+}
+
+impl NodeMapper for InBucket<'_> {
+    fn update(&self, node: usize) -> bool {
+        self.current[node].load(Ordering::Relaxed) == self.bucket
+    }
+}
+
+impl Buckets {
+    /// The bucket of nodes that are not in any bucket.
+    pub const NONE: usize = usize::MAX;
+
+    /// Puts every node into the bucket returned by `bucket`.
+    pub fn new(node_count: usize, bucket: impl Fn(usize) -> usize + Send + Sync) -> Self {
+        let mut buckets = Self {
+            current: par_vec(node_count, |_| AtomicUsize::new(Self::NONE)),
+            buckets: BTreeMap::new(),
+        };
+        let moves = (0..node_count)
+            .into_par_iter()
+            .map(|node| (node, bucket(node)))
+            .collect::<Vec<_>>();
+        buckets.update_batch(&moves);
+        buckets
+    }
+
+    /// Moves `node` into `bucket`, which may be [`Buckets::NONE`] to remove it.
+    pub fn update(&mut self, node: usize, bucket: usize) {
+        if std::mem::replace(self.current[node].get_mut(), bucket) == bucket {
+            return;
+        }
+        if bucket != Self::NONE {
+            self.buckets.entry(bucket).or_default().push(node);
+        }
+    }
+
+    /// Moves every node into its paired bucket, like [`Buckets::update`] for every pair.
+    ///
+    /// The moves are grouped by their bucket in parallel, every node may appear at most once.
+    pub fn update_batch(&mut self, moves: &[(usize, usize)]) {
+        let current = &self.current;
+        let mut moved = filter(moves, |&(node, bucket)| {
+            current[node].swap(bucket, Ordering::Relaxed) != bucket
+        });
+        moved.par_sort_unstable_by_key(|&(_, bucket)| bucket);
+
+        let buckets = moved
+            .par_iter()
+            .map(|&(_, bucket)| bucket)
+            .collect::<Vec<_>>();
+        let mut rest = moved.as_slice();
+        for (bucket, count) in histogram(&buckets) {
+            let (group, next) = rest.split_at(count);
+            rest = next;
+            if bucket != Self::NONE {
+                self.buckets
+                    .entry(bucket)
+                    .or_default()
+                    .extend(group.iter().map(|&(node, _)| node));
+            }
+        }
+    }
+
+    /// The bucket that `node` is currently in.
+    pub fn bucket(&self, node: usize) -> usize {
+        self.current[node].load(Ordering::Relaxed)
+    }
+
+    /// Removes the smallest non-empty bucket and returns its id and nodes.
+    ///
+    /// Buckets that are smaller than the last taken bucket can be filled again
+    /// and are returned by later calls.
+    pub fn next_bucket(&mut self) -> Option<(usize, NodeSubset)> {
+        self.next_bucket_with(&mut Workspace::default())
+    }
+
+    /// Like [`Buckets::next_bucket`], but with buffers from `workspace`.
+    pub fn next_bucket_with(&mut self, workspace: &mut Workspace) -> Option<(usize, NodeSubset)> {
+        while let Some((bucket, mut nodes)) = self.buckets.pop_first() {
+            // a node that left the bucket and came back has two entries
+            nodes.par_sort_unstable();
+            nodes.dedup();
+            let entries = NodeSubset::sparse(self.current.len(), nodes);
+            let nodes = node_filter_with(
+                &entries,
+                &InBucket {
+                    current: &self.current,
+                    bucket,
+                },
+                workspace,
+            );
+            workspace.recycle(entries);
+
+            if nodes.is_empty() {
+                workspace.recycle(nodes);
+                continue;
+            }
+            for &node in nodes.iter() {
+                *self.current[node].get_mut() = Self::NONE;
+            }
+            return Some((bucket, nodes));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(nodes: NodeSubset) -> Vec<usize> {
+        let mut nodes = nodes.iter().copied().collect::<Vec<_>>();
+        nodes.sort_unstable();
+        nodes
+    }
+
+    #[test]
+    fn buckets_in_order() {
+        let mut buckets = Buckets::new(6, |node| [3, 1, 3, Buckets::NONE, 1, 0][node]);
+
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (0, vec![5]));
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (1, vec![1, 4]));
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (3, vec![0, 2]));
+        assert!(buckets.next_bucket().is_none());
+    }
+
+    #[test]
+    fn buckets_update() {
+        let mut buckets = Buckets::new(4, |node| node + 1);
+        buckets.update(3, 2);
+        buckets.update(2, Buckets::NONE);
+        assert_eq!(buckets.bucket(3), 2);
+
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (1, vec![0]));
+        assert_eq!(buckets.bucket(0), Buckets::NONE);
+
+        // refill the bucket that was just taken
+        buckets.update(0, 1);
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (1, vec![0]));
+
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (2, vec![1, 3]));
+        assert!(buckets.next_bucket().is_none());
+    }
+
+    #[test]
+    fn buckets_update_batch() {
+        let mut buckets = Buckets::new(5, |node| node % 2);
+        buckets.update_batch(&[(0, 3), (1, 1), (2, Buckets::NONE), (4, 3)]);
+        assert_eq!(buckets.bucket(2), Buckets::NONE);
+
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (1, vec![1, 3]));
+        let (bucket, nodes) = buckets.next_bucket().unwrap();
+        assert_eq!((bucket, sorted(nodes)), (3, vec![0, 4]));
+        assert!(buckets.next_bucket().is_none());
+    }
+
+    #[quickcheck]
+    fn buckets_update_batch_like_update(initial: Vec<u8>, moves: Vec<(usize, u8)>) -> bool {
+        let node_count = initial.len();
+        if node_count == 0 {
+            return true;
+        }
+        let bucket = |value: u8| match value {
+            u8::MAX => Buckets::NONE,
+            value => usize::from(value % 16),
+        };
+        let mut moves = moves
+            .into_iter()
+            .map(|(node, value)| (node % node_count, bucket(value)))
+            .collect::<Vec<_>>();
+        moves.sort_unstable_by_key(|&(node, _)| node);
+        moves.dedup_by_key(|&mut (node, _)| node);
+
+        let mut batched = Buckets::new(node_count, |node| bucket(initial[node]));
+        let mut single = Buckets::new(node_count, |node| bucket(initial[node]));
+        batched.update_batch(&moves);
+        for &(node, bucket) in &moves {
+            single.update(node, bucket);
+        }
+
+        std::iter::from_fn(|| batched.next_bucket().map(|(b, nodes)| (b, sorted(nodes)))).eq(
+            std::iter::from_fn(|| single.next_bucket().map(|(b, nodes)| (b, sorted(nodes)))),
+        )
+    }
+}
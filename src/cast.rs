@@ -0,0 +1,69 @@
+//! Checked conversions between typed slices and their raw bytes.
+
+use crate::Result;
+use std::{mem, slice};
+
+/// Plain data that can be reinterpreted from and to its raw bytes.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` (or primitive), must not contain padding
+/// and every bit pattern must be a valid value.
+pub unsafe trait Pod: Copy + 'static {}
+
+unsafe impl Pod for usize {}
+unsafe impl Pod for i64 {}
+
+/// The in-memory representation of `values`.
+pub fn as_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    // SAFETY: `T` has no padding, so all bytes are initialized
+    unsafe { slice::from_raw_parts(values.as_ptr().cast(), mem::size_of_val(values)) }
+}
+
+/// Reinterprets `bytes` as values of `T`.
+///
+/// Fails if `bytes` is not aligned for `T` or its length is not a multiple of the size of `T`.
+pub fn cast_slice<T: Pod>(bytes: &[u8]) -> Result<&[T]> {
+    let size = mem::size_of::<T>();
+    let align = mem::align_of::<T>();
+    ensure!(
+        bytes.as_ptr() as usize % align == 0,
+        "data at {:p} is not aligned to {} bytes",
+        bytes.as_ptr(),
+        align
+    );
+    ensure!(
+        bytes.len() % size == 0,
+        "{} bytes are not a multiple of the value size of {} bytes",
+        bytes.len(),
+        size
+    );
+    // SAFETY: alignment and length are checked and every bit pattern is a valid `T`
+    Ok(unsafe { slice::from_raw_parts(bytes.as_ptr().cast(), bytes.len() / size) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[quickcheck]
+    fn test_cast_roundtrip(values: Vec<usize>) -> bool {
+        cast_slice::<usize>(as_bytes(&values)).unwrap() == values.as_slice()
+    }
+
+    #[test]
+    fn test_cast_misaligned() {
+        let values = [1_usize, 2, 3];
+        let bytes = as_bytes(&values);
+        assert!(cast_slice::<usize>(&bytes[1..9]).is_err());
+        assert_eq!(cast_slice::<usize>(&bytes[8..16]).unwrap(), &[2]);
+    }
+
+    #[test]
+    fn test_cast_truncated() {
+        let values = [1_usize, 2];
+        let bytes = as_bytes(&values);
+        assert!(cast_slice::<usize>(&bytes[..12]).is_err());
+        assert!(cast_slice::<usize>(&bytes[..0]).unwrap().is_empty());
+    }
+}
@@ -1,20 +1,77 @@
-use crate::{algos, graph, Result};
+use crate::run::{
+    self, Bfs, BfsValues, KCoreDecomposition, Mis, Output, RunOptions, Sssp, SsspAlgorithm,
+    SsspValues,
+};
+use ligrust::{
+    algos::{
+        BcConfig, BfsConfig, CcConfig, KCoreConfig, MisConfig, OutputFormat, PageRankConfig,
+        PageRankDeltaConfig, Parallelism, RadiiConfig, RunControl, TriangleConfig,
+    },
+    Result,
+};
 use pico_args::Arguments;
-use std::{ffi::OsStr, path::PathBuf};
+use rayon::ThreadPoolBuilder;
+use std::{
+    convert::Infallible,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc, OnceLock},
+    time::Duration,
+};
 
 struct Opts {
     command: Command,
+    threads: Threads,
+    limits: Limits,
 }
 
 impl Opts {
     fn parse_from_pico() -> Result<Self> {
         let mut args = Arguments::from_env();
 
-        fn as_path_buf(arg: &OsStr) -> Result<PathBuf, !> {
+        fn as_path_buf(arg: &OsStr) -> Result<PathBuf, Infallible> {
             Ok(PathBuf::from(arg))
         }
 
-        match args.subcommand()? {
+        // global options, taken before the subcommand so that they can appear anywhere
+        let threads = Threads {
+            threads: args.opt_value_from_str(["-t", "--threads"])?,
+            pin: args.contains("--pin"),
+            sweep: args.contains("--sweep"),
+        };
+        let limits = Limits {
+            timeout: args.opt_value_from_str("--timeout")?,
+            max_iterations: args.opt_value_from_str("--max-iterations")?,
+            progress: args.contains("--progress"),
+        };
+
+        fn run_options(mut args: Arguments) -> Result<RunOptions> {
+            let reverse = args.contains(["-r", "--reverse"]);
+            let trace = args.opt_value_from_str("--trace")?;
+            let output = args.opt_value_from_os_str(["-o", "--output"], as_path_buf)?;
+            let format = args.opt_value_from_str("--format")?;
+            if output.is_none() && format.is_some() {
+                bail!("--format requires --output");
+            }
+            let output = output.map(|path| Output {
+                format: format.unwrap_or_else(|| OutputFormat::from_path(&path)),
+                path,
+            });
+            let input = args.free_from_os_str(as_path_buf)?;
+            let free = args.finish();
+            if !free.is_empty() {
+                bail!("Unexpected arguments: {:?}", free);
+            }
+            Ok(RunOptions {
+                input,
+                reverse,
+                trace,
+                output,
+            })
+        }
+
+        let command = match args.subcommand()? {
             Some(c) if c.as_str() == "parse" => {
                 let output = args.value_from_os_str(["-o", "--output"], as_path_buf)?;
                 let input = args.free_from_os_str(as_path_buf)?;
@@ -22,92 +79,312 @@ impl Opts {
                 if !free.is_empty() {
                     bail!("Unexpected arguments: {:?}", free);
                 }
-                let command = Command::Parse(ParseInput { input, output });
-                Ok(Self { command })
+                Command::Parse(ParseInput { input, output })
             }
-            Some(c) if c.as_str() == "cc" => {
-                let input = args.free_from_os_str(as_path_buf)?;
-                let free = args.finish();
-                if !free.is_empty() {
-                    bail!("Unexpected arguments: {:?}", free);
+            Some(c) if c.as_str() == "cc" => Command::Cc(CcConfig::default(), run_options(args)?),
+            Some(c) if c.as_str() == "bfs" => {
+                let source = args.opt_value_from_str(["-s", "--source"])?;
+                let sources = args.opt_value_from_os_str("--sources", as_path_buf)?;
+                let config = match (source, sources) {
+                    (Some(source), None) => BfsConfig::new(source),
+                    (None, Some(sources)) => BfsConfig::from_sources(read_nodes(&sources)?),
+                    _ => bail!("bfs needs either --source or --sources"),
+                };
+                let bfs = Bfs {
+                    config,
+                    values: args
+                        .opt_value_from_str("--values")?
+                        .unwrap_or(BfsValues::Parents),
+                    tree: args.opt_value_from_os_str("--tree", as_path_buf)?,
+                };
+                Command::Bfs(bfs, run_options(args)?)
+            }
+            Some(c) if c.as_str() == "bc" => {
+                let sources = args.opt_value_from_os_str("--sources", as_path_buf)?;
+                let samples = args.opt_value_from_str("--samples")?;
+                let seed = args.opt_value_from_str("--seed")?;
+                let config = match (sources, samples) {
+                    (None, None) => BcConfig::default(),
+                    (Some(sources), None) => BcConfig::from_sources(read_nodes(&sources)?),
+                    (None, Some(samples)) => BcConfig::sampled(samples),
+                    (Some(_), Some(_)) => bail!("use either --sources or --samples"),
+                };
+                let config = match seed {
+                    Some(_) if samples.is_none() => bail!("--seed requires --samples"),
+                    Some(seed) => config.with_seed(seed),
+                    None => config,
+                };
+                Command::Bc(config, run_options(args)?)
+            }
+            Some(c) if c.as_str() == "radii" => {
+                let mut config = RadiiConfig::default();
+                if let Some(samples) = args.opt_value_from_str("--samples")? {
+                    config = config.with_samples(samples);
+                }
+                if let Some(seed) = args.opt_value_from_str("--seed")? {
+                    config = config.with_seed(seed);
                 }
-                let command = Command::CC(RunCC { input });
-                Ok(Self { command })
+                Command::Radii(config, run_options(args)?)
             }
-            Some(c) if c.as_str() == "bfs" => {
-                let source: usize = args.value_from_str(["-s", "--source"])?;
-                let input = args.free_from_os_str(as_path_buf)?;
-                let free = args.finish();
-                if !free.is_empty() {
-                    bail!("Unexpected arguments: {:?}", free);
+            Some(c) if c.as_str() == "kcore" => {
+                let core = args.opt_value_from_str("--core")?;
+                let subgraph = args.opt_value_from_os_str("--subgraph", as_path_buf)?;
+                if core.is_some() != subgraph.is_some() {
+                    bail!("--core and --subgraph have to be used together");
+                }
+                let kcore = KCoreDecomposition {
+                    config: KCoreConfig::default(),
+                    core,
+                    subgraph,
+                };
+                Command::KCore(kcore, run_options(args)?)
+            }
+            Some(c) if c.as_str() == "mis" => {
+                let mut config = MisConfig::default();
+                if let Some(seed) = args.opt_value_from_str("--seed")? {
+                    config = config.with_seed(seed);
+                }
+                let mis = Mis {
+                    config,
+                    verify: args.contains("--verify"),
+                };
+                Command::Mis(mis, run_options(args)?)
+            }
+            Some(c) if c.as_str() == "sssp" => {
+                let sssp = Sssp {
+                    algorithm: args.value_from_str("--algo")?,
+                    source: args.value_from_str(["-s", "--source"])?,
+                    delta: args.opt_value_from_str("--delta")?,
+                    values: args
+                        .opt_value_from_str("--values")?
+                        .unwrap_or(SsspValues::Distances),
+                };
+                if sssp.delta.is_some() && sssp.algorithm != SsspAlgorithm::DeltaStepping {
+                    bail!("--delta requires --algo delta-stepping");
                 }
-                let command = Command::BFS(RunBFS { input, source });
-                Ok(Self { command })
+                Command::Sssp(sssp, run_options(args)?)
+            }
+            Some(c) if c.as_str() == "triangles" => {
+                let options = run_options(args)?;
+                // per-node counts are only computed when they are written
+                let config = TriangleConfig::default().with_node_counts(options.output.is_some());
+                Command::Triangles(config, options)
+            }
+            Some(c) if c.as_str() == "pr" => {
+                let mut config = PageRankConfig::default();
+                if let Some(damping) = args.opt_value_from_str("--damping")? {
+                    config = config.with_damping(damping);
+                }
+                if let Some(tolerance) = args.opt_value_from_str("--tolerance")? {
+                    config = config.with_tolerance(tolerance);
+                }
+                if let Some(max_iterations) = args.opt_value_from_str(["-i", "--iterations"])? {
+                    config = config.with_max_iterations(max_iterations);
+                }
+                Command::PageRank(config, run_options(args)?)
             }
             Some(c) if c.as_str() == "prd" => {
-                let max_iterations: usize = args.value_from_str(["-i", "--iterations"])?;
-                let input = args.free_from_os_str(as_path_buf)?;
-                let free = args.finish();
-                if !free.is_empty() {
-                    bail!("Unexpected arguments: {:?}", free);
+                let mut config = PageRankDeltaConfig::default();
+                if let Some(damping) = args.opt_value_from_str("--damping")? {
+                    config = config.with_damping(damping);
+                }
+                if let Some(tolerance) = args.opt_value_from_str("--tolerance")? {
+                    config = config.with_tolerance(tolerance);
+                }
+                if let Some(max_iterations) = args.opt_value_from_str(["-i", "--iterations"])? {
+                    config = config.with_max_iterations(max_iterations);
                 }
-                let command = Command::PageRankDelta(RunPageRankDelta {
-                    input,
-                    max_iterations,
-                });
-                Ok(Self { command })
+                Command::PageRankDelta(config, run_options(args)?)
             }
             _ => {
-                bail!("invalid command, use either parse, cc or bfs")
+                bail!("invalid command, use either parse, cc, bfs, bc, radii, kcore, mis, sssp, triangles, pr or prd")
+            }
+        };
+
+        Ok(Self {
+            command,
+            threads,
+            limits,
+        })
+    }
+}
+
+/// Reads whitespace separated node ids.
+fn read_nodes(path: &Path) -> Result<Vec<usize>> {
+    let nodes = fs::read_to_string(path)?;
+    nodes
+        .split_whitespace()
+        .map(|node| match node.parse() {
+            Ok(node) => Ok(node),
+            Err(_) => bail!("invalid node id {:?} in {}", node, path.display()),
+        })
+        .collect()
+}
+
+/// Global options for the thread pools of the algorithms
+struct Threads {
+    /// size of the global pool, rayon's default if not set
+    threads: Option<usize>,
+    /// pin every thread to its own cpu
+    pin: bool,
+    /// rerun the algorithm with 1, 2, 4, ... threads up to the size of the global pool
+    sweep: bool,
+}
+
+impl Threads {
+    fn build(self) -> Result<Parallelism> {
+        if self.pin && !cfg!(target_os = "linux") {
+            bail!("--pin is only supported on Linux");
+        }
+
+        let builder = |threads: usize| {
+            let builder = ThreadPoolBuilder::new().num_threads(threads);
+            if self.pin {
+                builder.start_handler(pin_thread)
+            } else {
+                builder
             }
+        };
+
+        if self.threads.is_some() || self.pin {
+            builder(self.threads.unwrap_or(0)).build_global()?;
+        }
+
+        if !self.sweep {
+            return Ok(Parallelism::Global);
         }
+
+        Ok(Parallelism::sweep(rayon::current_num_threads(), builder)?)
     }
 }
 
-enum Command {
-    Parse(ParseInput),
-    CC(RunCC),
-    BFS(RunBFS),
-    PageRankDelta(RunPageRankDelta),
+/// Global options that limit and observe the iterations of the algorithms
+struct Limits {
+    /// stop after this many seconds
+    timeout: Option<f64>,
+    /// stop after this many iterations
+    max_iterations: Option<usize>,
+    /// print the frontier size of every iteration to stderr
+    progress: bool,
 }
 
-/// Parses an input file and dump a binary representation of the graph
-struct ParseInput {
-    /// input file in "AdjacencyGraph" format
-    input: PathBuf,
+impl Limits {
+    fn build(self) -> Result<RunControl> {
+        let mut control = RunControl::new().with_cancel_flag(cancel_on_interrupt()?);
+        if let Some(timeout) = self.timeout {
+            control = control.with_max_duration(Duration::try_from_secs_f64(timeout)?);
+        }
+        if let Some(max_iterations) = self.max_iterations {
+            control = control.with_max_iterations(max_iterations);
+        }
+        if self.progress {
+            control = control.with_progress(|progress| {
+                eprintln!(
+                    "iteration {}: {} nodes in the frontier after {:?}",
+                    progress.iteration, progress.frontier_size, progress.elapsed
+                )
+            });
+        }
+        Ok(control)
+    }
+}
 
-    /// output file where to dump the graph to
-    output: PathBuf,
+static CANCEL_FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Returns a flag that is set on the first Ctrl-C, the second one exits immediately.
+#[cfg(unix)]
+fn cancel_on_interrupt() -> Result<Arc<AtomicBool>> {
+    extern "C" fn on_interrupt(_signal: libc::c_int) {
+        const MESSAGE: &[u8] = b"interrupted, stopping after the current iteration\n";
+        if let Some(cancelled) = CANCEL_FLAG.get() {
+            if cancelled.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                unsafe { libc::_exit(130) };
+            }
+            // only async-signal-safe functions may be called here
+            unsafe { libc::write(libc::STDERR_FILENO, MESSAGE.as_ptr().cast(), MESSAGE.len()) };
+        }
+    }
+
+    let cancelled = Arc::clone(CANCEL_FLAG.get_or_init(Arc::default));
+    let handler = on_interrupt as extern "C" fn(libc::c_int);
+    if unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) } == libc::SIG_ERR {
+        bail!("could not install the Ctrl-C handler");
+    }
+    Ok(cancelled)
 }
 
-/// Run conncected components on a parsed input
-struct RunCC {
-    /// input file in "AdjacencyGraph" format
-    input: PathBuf,
+#[cfg(not(unix))]
+fn cancel_on_interrupt() -> Result<Arc<AtomicBool>> {
+    Ok(Arc::clone(CANCEL_FLAG.get_or_init(Arc::default)))
 }
 
-/// Run BFS on a parsed input
-struct RunBFS {
-    /// input file in "AdjacencyGraph" format
-    input: PathBuf,
-    /// source node to run BFS from
-    source: usize,
+/// Pins the thread with the given pool index to one of the cpus this process may run on.
+#[cfg(target_os = "linux")]
+fn pin_thread(index: usize) {
+    unsafe {
+        let mut allowed = std::mem::zeroed::<libc::cpu_set_t>();
+        if libc::sched_getaffinity(0, std::mem::size_of_val(&allowed), &mut allowed) != 0 {
+            eprintln!(
+                "could not read the cpu affinity, thread {} is not pinned",
+                index
+            );
+            return;
+        }
+
+        let cpus = (0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &allowed))
+            .collect::<Vec<_>>();
+        let cpu = cpus[index % cpus.len()];
+
+        let mut pinned = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_SET(cpu, &mut pinned);
+        if libc::sched_setaffinity(0, std::mem::size_of_val(&pinned), &pinned) != 0 {
+            eprintln!("could not pin thread {} to cpu {}", index, cpu);
+        }
+    }
 }
 
-/// Run PageRankDelta on a parsed input
-struct RunPageRankDelta {
+#[cfg(not(target_os = "linux"))]
+fn pin_thread(_index: usize) {}
+
+enum Command {
+    Parse(ParseInput),
+    Cc(CcConfig, RunOptions),
+    Bfs(Bfs, RunOptions),
+    Bc(BcConfig, RunOptions),
+    Radii(RadiiConfig, RunOptions),
+    KCore(KCoreDecomposition, RunOptions),
+    Mis(Mis, RunOptions),
+    Sssp(Sssp, RunOptions),
+    Triangles(TriangleConfig, RunOptions),
+    PageRank(PageRankConfig, RunOptions),
+    PageRankDelta(PageRankDeltaConfig, RunOptions),
+}
+
+/// Parses an input file and dump a binary representation of the graph
+struct ParseInput {
     /// input file in "AdjacencyGraph" format
     input: PathBuf,
-    /// maximum number of iterations to run
-    max_iterations: usize,
+
+    /// output file where to dump the graph to
+    output: PathBuf,
 }
 
 pub fn main() -> Result<()> {
     let opts = Opts::parse_from_pico()?;
+    let parallelism = opts.threads.build()?;
+    let control = opts.limits.build()?;
     match opts.command {
-        Command::Parse(opts) => graph::parse(opts.input, opts.output),
-        Command::CC(opts) => algos::run_cc(opts.input),
-        Command::BFS(opts) => algos::run_bfs(opts.input, opts.source),
-        Command::PageRankDelta(opts) => algos::run_page_rank_delta(opts.input, opts.max_iterations),
+        Command::Parse(opts) => run::parse(opts.input, opts.output),
+        Command::Cc(config, opts) => run::run(&config, opts, &parallelism, &control),
+        Command::Bfs(bfs, opts) => run::run(&bfs, opts, &parallelism, &control),
+        Command::Bc(config, opts) => run::run(&config, opts, &parallelism, &control),
+        Command::Radii(config, opts) => run::run(&config, opts, &parallelism, &control),
+        Command::KCore(kcore, opts) => run::run(&kcore, opts, &parallelism, &control),
+        Command::Mis(mis, opts) => run::run(&mis, opts, &parallelism, &control),
+        Command::Sssp(sssp, opts) => run::run_weighted(&sssp, opts, &parallelism, &control),
+        Command::Triangles(config, opts) => run::run(&config, opts, &parallelism, &control),
+        Command::PageRank(config, opts) => run::run(&config, opts, &parallelism, &control),
+        Command::PageRankDelta(config, opts) => run::run(&config, opts, &parallelism, &control),
     }
 }
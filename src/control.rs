@@ -0,0 +1,229 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Limits and observes the iterations of an algorithm.
+///
+/// Algorithms call [`RunControl::start`] once and poll the returned [`Running`]
+/// before every iteration, so a run stops at the next iteration boundary
+/// and returns the results computed so far.
+#[derive(Default)]
+pub struct RunControl {
+    cancelled: Arc<AtomicBool>,
+    max_duration: Option<Duration>,
+    max_iterations: Option<usize>,
+    progress: Option<ProgressCallback>,
+}
+
+type ProgressCallback = Box<dyn Fn(&Progress) + Send + Sync>;
+
+/// Passed to the progress callback before every iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub iteration: usize,
+    pub frontier_size: usize,
+    pub elapsed: Duration,
+}
+
+/// Why a run stopped before the algorithm finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Cancelled,
+    TimedOut,
+    MaxIterations,
+}
+
+/// What happened during a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunStats {
+    pub iterations: usize,
+    pub elapsed: Duration,
+    /// `None` if the algorithm ran to completion.
+    pub stopped: Option<Stop>,
+}
+
+impl RunControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `cancelled` as the cancellation flag, so that it can be shared with other code.
+    pub fn with_cancel_flag(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = cancelled;
+        self
+    }
+
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Stops all runs of this control at their next iteration.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn start(&self) -> Running<'_> {
+        Running {
+            control: self,
+            start: Instant::now(),
+            iterations: 0,
+            stopped: None,
+        }
+    }
+}
+
+/// A single run of an algorithm, see [`RunControl::start`].
+pub struct Running<'a> {
+    control: &'a RunControl,
+    start: Instant,
+    iterations: usize,
+    stopped: Option<Stop>,
+}
+
+impl<'a> Running<'a> {
+    /// Returns whether the next iteration may run and reports its progress if so.
+    pub fn next_iteration(&mut self, frontier_size: usize) -> bool {
+        let elapsed = self.start.elapsed();
+
+        self.stopped = if self.control.is_cancelled() {
+            Some(Stop::Cancelled)
+        } else if self.control.max_duration.is_some_and(|max| elapsed >= max) {
+            Some(Stop::TimedOut)
+        } else if self
+            .control
+            .max_iterations
+            .is_some_and(|max| self.iterations >= max)
+        {
+            Some(Stop::MaxIterations)
+        } else {
+            None
+        };
+
+        if self.stopped.is_some() {
+            return false;
+        }
+
+        if let Some(progress) = self.control.progress.as_ref() {
+            progress(&Progress {
+                iteration: self.iterations,
+                frontier_size,
+                elapsed,
+            });
+        }
+        self.iterations += 1;
+        true
+    }
+
+    pub fn finish(self) -> RunStats {
+        RunStats {
+            iterations: self.iterations,
+            elapsed: self.start.elapsed(),
+            stopped: self.stopped,
+        }
+    }
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Cancelled => f.write_str("cancelled"),
+            Stop::TimedOut => f.write_str("timed out"),
+            Stop::MaxIterations => f.write_str("stopped at the maximum number of iterations"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn run_to_end(control: &RunControl) -> RunStats {
+        let mut running = control.start();
+        let mut frontier_size = 10;
+        while frontier_size != 0 && running.next_iteration(frontier_size) {
+            frontier_size -= 1;
+        }
+        running.finish()
+    }
+
+    #[test]
+    fn run_control_completes() {
+        let stats = run_to_end(&RunControl::new());
+        assert_eq!(stats.iterations, 10);
+        assert_eq!(stats.stopped, None);
+    }
+
+    #[test]
+    fn run_control_max_iterations() {
+        let stats = run_to_end(&RunControl::new().with_max_iterations(3));
+        assert_eq!(stats.iterations, 3);
+        assert_eq!(stats.stopped, Some(Stop::MaxIterations));
+    }
+
+    #[test]
+    fn run_control_max_duration() {
+        let stats = run_to_end(&RunControl::new().with_max_duration(Duration::ZERO));
+        assert_eq!(stats.iterations, 0);
+        assert_eq!(stats.stopped, Some(Stop::TimedOut));
+    }
+
+    #[test]
+    fn run_control_cancel() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let control = RunControl::new().with_cancel_flag(Arc::clone(&cancelled));
+
+        let mut running = control.start();
+        assert!(running.next_iteration(1));
+        cancelled.store(true, Ordering::Relaxed);
+        assert!(!running.next_iteration(1));
+        assert!(control.is_cancelled());
+
+        let stats = running.finish();
+        assert_eq!(stats.iterations, 1);
+        assert_eq!(stats.stopped, Some(Stop::Cancelled));
+    }
+
+    #[test]
+    fn run_control_progress() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let control = RunControl::new().with_progress({
+            let seen = Arc::clone(&seen);
+            move |progress| {
+                seen.lock()
+                    .unwrap()
+                    .push((progress.iteration, progress.frontier_size))
+            }
+        });
+
+        run_to_end(&control);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 10);
+        assert_eq!(seen[0], (0, 10));
+        assert_eq!(seen[9], (9, 1));
+    }
+}
@@ -1,20 +1,25 @@
 use crate::Result;
-use atoi::FromRadix10;
+use atoi::{FromRadix10, FromRadix10Signed};
 use byte_slice_cast::*;
 use linereader::LineReader;
 #[cfg(feature = "mapped_graph")]
 use memmap::Mmap;
-#[cfg(feature = "mapped_graph")]
-use std::convert::TryInto;
+use rayon::prelude::*;
 use std::{
     convert::TryFrom,
     fs::File,
     io::{Read, Write},
     path::PathBuf,
     slice,
-    time::Instant,
 };
 
+#[path = "cast.rs"]
+#[cfg_attr(not(feature = "mapped_graph"), allow(dead_code))]
+mod cast;
+#[cfg(feature = "mapped_graph")]
+use cast::cast_slice;
+use cast::{as_bytes, Pod};
+
 pub trait Graph {
     fn node_count(&self) -> usize;
 
@@ -31,21 +36,189 @@ pub trait Graph {
     fn threshold(&self) -> usize {
         self.rel_count() / 20
     }
+
+    /// Whether the `index`-th relationship in `out(source)` is visible.
+    ///
+    /// Views like [`FilteredGraph`] hide relationships without materializing a new graph,
+    /// so `out` and `inc` may still contain relationships that have to be skipped.
+    fn contains_out(&self, _source: usize, _index: usize) -> bool {
+        true
+    }
+
+    /// Whether the `index`-th relationship in `inc(target)` is visible.
+    fn contains_inc(&self, _target: usize, _index: usize) -> bool {
+        true
+    }
+}
+
+pub type Weight = i64;
+
+/// A graph that stores a weight for every relationship.
+///
+/// The weights are aligned with the targets returned from [`Graph::out`] and [`Graph::inc`],
+/// i.e. `out_weights(node)[i]` is the weight of the relationship to `out(node)[i]`.
+pub trait WeightedGraph: Graph {
+    fn out_weights(&self, node: usize) -> &[Weight];
+
+    fn inc_weights(&self, node: usize) -> &[Weight];
 }
+
+/// A graph that is read directly from a memory mapped file written by [`dump`].
+///
+/// The file is validated once when it is mapped, the typed views into it borrow from the mapping.
 #[cfg(feature = "mapped_graph")]
 #[derive(Debug)]
-pub struct MappedGraph {
-    map: Mmap,
+pub struct MappedGraph<'a> {
     node_count: usize,
     rel_count: usize,
-    out_nodes: &'static [Node],
-    out_targets: &'static [usize],
-    in_nodes: &'static [Node],
-    in_targets: &'static [usize],
+    out: MappedList<'a>,
+    inc: MappedList<'a>,
+}
+
+#[cfg(feature = "mapped_graph")]
+#[derive(Debug)]
+struct MappedList<'a> {
+    nodes: &'a [Node],
+    targets: &'a [usize],
+    /// Either empty or one weight per target
+    weights: &'a [Weight],
+}
+
+/// Takes `len` values of `T` from `bytes`, starting at `offset`, and moves `offset` past them.
+#[cfg(feature = "mapped_graph")]
+fn take<'a, T: Pod>(
+    bytes: &'a [u8],
+    offset: &mut usize,
+    len: usize,
+    name: &str,
+) -> Result<&'a [T]> {
+    let start = *offset;
+    let end = len
+        .checked_mul(std::mem::size_of::<T>())
+        .and_then(|size| size.checked_add(start))
+        .filter(|&end| end <= bytes.len());
+    let end = match end {
+        Some(end) => end,
+        None => bail!(
+            "graph file is truncated, {} {} do not fit into {} bytes after offset {}",
+            len,
+            name,
+            bytes.len().saturating_sub(start),
+            start
+        ),
+    };
+    let values =
+        cast_slice::<T>(&bytes[start..end]).map_err(|e| e.wrap_err(format!("invalid {}", name)))?;
+    *offset = end;
+    Ok(values)
+}
+
+#[cfg(feature = "mapped_graph")]
+impl<'a> MappedGraph<'a> {
+    /// Validates the header, the size and alignment of every section
+    /// and that every relationship list lies within its section.
+    fn new(bytes: &'a [u8]) -> Result<Self> {
+        let mut offset = 0;
+        let (node_count, rel_count) = match *take::<usize>(bytes, &mut offset, 2, "header values")?
+        {
+            [node_count, rel_count] => (node_count, rel_count),
+            _ => unreachable!("header has two values"),
+        };
+
+        let out_nodes = take(bytes, &mut offset, node_count, "outgoing nodes")?;
+        let out_targets = take(bytes, &mut offset, rel_count, "outgoing targets")?;
+        let in_nodes = take(bytes, &mut offset, node_count, "incoming nodes")?;
+        let in_targets = take(bytes, &mut offset, rel_count, "incoming targets")?;
+
+        let weights_len = if offset == bytes.len() { 0 } else { rel_count };
+        let out_weights = take(bytes, &mut offset, weights_len, "outgoing weights")?;
+        let in_weights = take(bytes, &mut offset, weights_len, "incoming weights")?;
+
+        ensure!(
+            offset == bytes.len(),
+            "graph file has {} bytes of extra data",
+            bytes.len() - offset
+        );
+
+        let out = MappedList {
+            nodes: out_nodes,
+            targets: out_targets,
+            weights: out_weights,
+        };
+        let inc = MappedList {
+            nodes: in_nodes,
+            targets: in_targets,
+            weights: in_weights,
+        };
+        out.validate(node_count, "outgoing")?;
+        inc.validate(node_count, "incoming")?;
+
+        Ok(MappedGraph {
+            node_count,
+            rel_count,
+            out,
+            inc,
+        })
+    }
+
+    pub fn is_weighted(&self) -> bool {
+        self.out.weights.len() == self.rel_count
+    }
 }
 
 #[cfg(feature = "mapped_graph")]
-impl Graph for MappedGraph {
+impl<'a> MappedList<'a> {
+    /// Checks that every list lies within `targets` and every target is a node.
+    fn validate(&self, node_count: usize, name: &str) -> Result<()> {
+        let rel_count = self.targets.len();
+        if let Some(node) = self.nodes.par_iter().position_any(|node| {
+            node.offset
+                .checked_add(node.degree)
+                .is_none_or(|end| end > rel_count)
+        }) {
+            let Node { offset, degree } = self.nodes[node];
+            bail!(
+                "{} relationships of node {} at {}..{}+{} exceed the {} relationships of the graph",
+                name,
+                node,
+                offset,
+                offset,
+                degree,
+                rel_count
+            );
+        }
+        if let Some(&target) = self
+            .targets
+            .par_iter()
+            .find_any(|&&target| target >= node_count)
+        {
+            bail!(
+                "{} relationship to node {} exceeds the {} nodes of the graph",
+                name,
+                target,
+                node_count
+            );
+        }
+        Ok(())
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.nodes[node].degree
+    }
+
+    fn rels(&self, node: usize) -> &'a [usize] {
+        let Node { offset, degree } = self.nodes[node];
+        &self.targets[offset..offset + degree]
+    }
+
+    fn weights(&self, node: usize) -> &'a [Weight] {
+        let Node { offset, degree } = self.nodes[node];
+        &self.weights[offset..offset + degree]
+    }
+}
+
+#[cfg(feature = "mapped_graph")]
+impl Graph for MappedGraph<'_> {
     fn node_count(&self) -> usize {
         self.node_count
     }
@@ -55,25 +228,32 @@ impl Graph for MappedGraph {
     }
 
     fn out(&self, node: usize) -> &[usize] {
-        let node = self.out_nodes[node];
-        let start = node.offset;
-        let end = node.degree + start;
-        &self.out_targets[start..end]
+        self.out.rels(node)
     }
 
     fn inc(&self, node: usize) -> &[usize] {
-        let node = self.in_nodes[node];
-        let start = node.offset;
-        let end = node.degree + start;
-        &self.in_targets[start..end]
+        self.inc.rels(node)
     }
 
     fn out_degree(&self, node: usize) -> usize {
-        self.out_nodes[node].degree
+        self.out.degree(node)
     }
 
     fn inc_degree(&self, node: usize) -> usize {
-        self.in_nodes[node].degree
+        self.inc.degree(node)
+    }
+}
+
+#[cfg(feature = "mapped_graph")]
+impl WeightedGraph for MappedGraph<'_> {
+    fn out_weights(&self, node: usize) -> &[Weight] {
+        assert!(self.is_weighted(), "graph has no relationship weights");
+        self.out.weights(node)
+    }
+
+    fn inc_weights(&self, node: usize) -> &[Weight] {
+        assert!(self.is_weighted(), "graph has no relationship weights");
+        self.inc.weights(node)
     }
 }
 
@@ -109,10 +289,360 @@ impl Graph for AdjacencyGraph {
     }
 }
 
+impl WeightedGraph for AdjacencyGraph {
+    fn out_weights(&self, node: usize) -> &[Weight] {
+        self.out.weights(node)
+    }
+
+    fn inc_weights(&self, node: usize) -> &[Weight] {
+        self.inc.weights(node)
+    }
+}
+
+impl AdjacencyGraph {
+    pub fn is_weighted(&self) -> bool {
+        self.out.is_weighted()
+    }
+
+    /// Copies the visible relationships of `graph`, e.g. to materialize a [`FilteredGraph`].
+    /// Node ids are kept and weights are not copied.
+    pub fn from_graph<G: Graph>(graph: &G) -> Self {
+        let mut offsets = Vec::with_capacity(graph.node_count());
+        let mut targets = Vec::new();
+        for node in 0..graph.node_count() {
+            offsets.push(targets.len());
+            targets.extend(
+                graph
+                    .out(node)
+                    .iter()
+                    .enumerate()
+                    .filter(|&(index, _)| graph.contains_out(node, index))
+                    .map(|(_, &target)| target),
+            );
+        }
+        AdjacencyGraph::from(AdjacencyList::from((offsets, targets)))
+    }
+}
+
+impl<G: Graph + ?Sized> Graph for &G {
+    fn node_count(&self) -> usize {
+        (**self).node_count()
+    }
+
+    fn rel_count(&self) -> usize {
+        (**self).rel_count()
+    }
+
+    fn out(&self, node: usize) -> &[usize] {
+        (**self).out(node)
+    }
+
+    fn inc(&self, node: usize) -> &[usize] {
+        (**self).inc(node)
+    }
+
+    fn out_degree(&self, node: usize) -> usize {
+        (**self).out_degree(node)
+    }
+
+    fn inc_degree(&self, node: usize) -> usize {
+        (**self).inc_degree(node)
+    }
+
+    fn threshold(&self) -> usize {
+        (**self).threshold()
+    }
+
+    fn contains_out(&self, source: usize, index: usize) -> bool {
+        (**self).contains_out(source, index)
+    }
+
+    fn contains_inc(&self, target: usize, index: usize) -> bool {
+        (**self).contains_inc(target, index)
+    }
+}
+
+impl<G: WeightedGraph + ?Sized> WeightedGraph for &G {
+    fn out_weights(&self, node: usize) -> &[Weight] {
+        (**self).out_weights(node)
+    }
+
+    fn inc_weights(&self, node: usize) -> &[Weight] {
+        (**self).inc_weights(node)
+    }
+}
+
+/// A view of the reverse graph, with outgoing and incoming relationships swapped.
+#[derive(Debug)]
+pub struct Transposed<G>(G);
+
+impl<G: Graph> Transposed<G> {
+    pub fn new(graph: G) -> Self {
+        Transposed(graph)
+    }
+
+    pub fn into_inner(self) -> G {
+        self.0
+    }
+}
+
+impl<G: Graph> Graph for Transposed<G> {
+    fn node_count(&self) -> usize {
+        self.0.node_count()
+    }
+
+    fn rel_count(&self) -> usize {
+        self.0.rel_count()
+    }
+
+    fn out(&self, node: usize) -> &[usize] {
+        self.0.inc(node)
+    }
+
+    fn inc(&self, node: usize) -> &[usize] {
+        self.0.out(node)
+    }
+
+    fn out_degree(&self, node: usize) -> usize {
+        self.0.inc_degree(node)
+    }
+
+    fn inc_degree(&self, node: usize) -> usize {
+        self.0.out_degree(node)
+    }
+
+    fn threshold(&self) -> usize {
+        self.0.threshold()
+    }
+
+    fn contains_out(&self, source: usize, index: usize) -> bool {
+        self.0.contains_inc(source, index)
+    }
+
+    fn contains_inc(&self, target: usize, index: usize) -> bool {
+        self.0.contains_out(target, index)
+    }
+}
+
+impl<G: WeightedGraph> WeightedGraph for Transposed<G> {
+    fn out_weights(&self, node: usize) -> &[Weight] {
+        self.0.inc_weights(node)
+    }
+
+    fn inc_weights(&self, node: usize) -> &[Weight] {
+        self.0.out_weights(node)
+    }
+}
+
+type NodePredicate = Box<dyn Fn(usize) -> bool + Send + Sync>;
+type RelationshipPredicate = Box<dyn Fn(usize, usize) -> bool + Send + Sync>;
+
+/// A view that hides nodes and relationships of the wrapped graph.
+///
+/// Filters are applied lazily by [`Graph::contains_out`] and [`Graph::contains_inc`],
+/// which are respected by the ligra engine. Hidden nodes keep their ids and have no
+/// relationships. Degrees and the relationship count are those of the visible
+/// relationships, they are counted again whenever a filter is added.
+pub struct FilteredGraph<G> {
+    graph: G,
+    node_predicate: Option<NodePredicate>,
+    rel_predicate: Option<RelationshipPredicate>,
+    weight_threshold: Option<WeightThreshold<G>>,
+    /// The visible degrees, `None` as long as no filter was added
+    degrees: Option<FilteredDegrees>,
+}
+
+struct FilteredDegrees {
+    out: Box<[usize]>,
+    inc: Box<[usize]>,
+    rel_count: usize,
+}
+
+struct WeightThreshold<G> {
+    threshold: Weight,
+    out_weight: fn(&G, usize, usize) -> Weight,
+    inc_weight: fn(&G, usize, usize) -> Weight,
+}
+
+impl<G: Graph + Sync> FilteredGraph<G> {
+    pub fn new(graph: G) -> Self {
+        Self {
+            graph,
+            node_predicate: None,
+            rel_predicate: None,
+            weight_threshold: None,
+            degrees: None,
+        }
+    }
+
+    /// Hides all nodes for which `predicate` returns false, together with their relationships.
+    /// Multiple node filters are combined with a logical and.
+    pub fn with_node_filter(
+        mut self,
+        predicate: impl Fn(usize) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.node_predicate = Some(match self.node_predicate.take() {
+            Some(previous) => Box::new(move |node| previous(node) && predicate(node)),
+            None => Box::new(predicate),
+        });
+        self.count_degrees()
+    }
+
+    /// Hides all relationships for which `predicate(source, target)` returns false.
+    /// Multiple relationship filters are combined with a logical and.
+    pub fn with_relationship_filter(
+        mut self,
+        predicate: impl Fn(usize, usize) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.rel_predicate = Some(match self.rel_predicate.take() {
+            Some(previous) => Box::new(move |source, target| {
+                previous(source, target) && predicate(source, target)
+            }),
+            None => Box::new(predicate),
+        });
+        self.count_degrees()
+    }
+
+    pub fn into_inner(self) -> G {
+        self.graph
+    }
+
+    fn count_degrees(mut self) -> Self {
+        self.degrees = None;
+        let visible = |len: usize, contains: &(dyn Fn(usize) -> bool + Sync)| {
+            (0..len).filter(|&index| contains(index)).count()
+        };
+        let out = (0..self.node_count())
+            .into_par_iter()
+            .map(|node| {
+                visible(self.graph.out(node).len(), &|index| {
+                    self.contains_out(node, index)
+                })
+            })
+            .collect::<Vec<_>>();
+        let inc = (0..self.node_count())
+            .into_par_iter()
+            .map(|node| {
+                visible(self.graph.inc(node).len(), &|index| {
+                    self.contains_inc(node, index)
+                })
+            })
+            .collect::<Vec<_>>();
+        let rel_count = out.par_iter().sum();
+        self.degrees = Some(FilteredDegrees {
+            out: out.into_boxed_slice(),
+            inc: inc.into_boxed_slice(),
+            rel_count,
+        });
+        self
+    }
+}
+
+impl<G> FilteredGraph<G> {
+    fn contains(&self, source: usize, target: usize) -> bool {
+        self.node_predicate
+            .as_ref()
+            .is_none_or(|predicate| predicate(source) && predicate(target))
+            && self
+                .rel_predicate
+                .as_ref()
+                .is_none_or(|predicate| predicate(source, target))
+    }
+}
+
+impl<G: WeightedGraph + Sync> FilteredGraph<G> {
+    /// Hides all relationships with a weight less than or equal to `threshold`.
+    pub fn with_weight_above(mut self, threshold: Weight) -> Self {
+        let threshold = match self.weight_threshold {
+            Some(WeightThreshold {
+                threshold: previous,
+                ..
+            }) => threshold.max(previous),
+            None => threshold,
+        };
+        self.weight_threshold = Some(WeightThreshold {
+            threshold,
+            out_weight: |graph, source, index| graph.out_weights(source)[index],
+            inc_weight: |graph, target, index| graph.inc_weights(target)[index],
+        });
+        self.count_degrees()
+    }
+}
+
+impl<G: Graph> Graph for FilteredGraph<G> {
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    fn rel_count(&self) -> usize {
+        match &self.degrees {
+            Some(degrees) => degrees.rel_count,
+            None => self.graph.rel_count(),
+        }
+    }
+
+    fn out(&self, node: usize) -> &[usize] {
+        self.graph.out(node)
+    }
+
+    fn inc(&self, node: usize) -> &[usize] {
+        self.graph.inc(node)
+    }
+
+    fn out_degree(&self, node: usize) -> usize {
+        match &self.degrees {
+            Some(degrees) => degrees.out[node],
+            None => self.graph.out_degree(node),
+        }
+    }
+
+    fn inc_degree(&self, node: usize) -> usize {
+        match &self.degrees {
+            Some(degrees) => degrees.inc[node],
+            None => self.graph.inc_degree(node),
+        }
+    }
+
+    fn threshold(&self) -> usize {
+        match &self.degrees {
+            Some(degrees) => degrees.rel_count / 20,
+            None => self.graph.threshold(),
+        }
+    }
+
+    fn contains_out(&self, source: usize, index: usize) -> bool {
+        self.graph.contains_out(source, index)
+            && self.contains(source, self.graph.out(source)[index])
+            && self.weight_threshold.as_ref().is_none_or(|weights| {
+                (weights.out_weight)(&self.graph, source, index) > weights.threshold
+            })
+    }
+
+    fn contains_inc(&self, target: usize, index: usize) -> bool {
+        self.graph.contains_inc(target, index)
+            && self.contains(self.graph.inc(target)[index], target)
+            && self.weight_threshold.as_ref().is_none_or(|weights| {
+                (weights.inc_weight)(&self.graph, target, index) > weights.threshold
+            })
+    }
+}
+
+impl<G: WeightedGraph> WeightedGraph for FilteredGraph<G> {
+    fn out_weights(&self, node: usize) -> &[Weight] {
+        self.graph.out_weights(node)
+    }
+
+    fn inc_weights(&self, node: usize) -> &[Weight] {
+        self.graph.inc_weights(node)
+    }
+}
+
 #[derive(Debug)]
 pub struct AdjacencyList {
     nodes: Box<[Node]>,
     targets: Box<[usize]>,
+    /// Either empty or one weight per target
+    weights: Box<[Weight]>,
 }
 
 impl AdjacencyList {
@@ -134,14 +664,34 @@ impl AdjacencyList {
         let end = node.degree + start;
         &self.targets[start..end]
     }
+
+    pub fn is_weighted(&self) -> bool {
+        self.weights.len() == self.targets.len()
+    }
+
+    pub fn weights(&self, node: usize) -> &[Weight] {
+        assert!(self.is_weighted(), "graph has no relationship weights");
+        let node = self.nodes[node];
+        let start = node.offset;
+        let end = node.degree + start;
+        &self.weights[start..end]
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
+#[repr(C)]
 pub struct Node {
     degree: usize,
     offset: usize,
 }
 
+unsafe impl Pod for Node {}
+
+const ADJACENCY_GRAPH_HEADER: &[u8] = b"AdjacencyGraph\n";
+const WEIGHTED_ADJACENCY_GRAPH_HEADER: &[u8] = b"WeightedAdjacencyGraph\n";
+
+/// Parses either an "AdjacencyGraph" or a "WeightedAdjacencyGraph" file,
+/// the latter being followed by one weight per target.
 impl<R> TryFrom<LineReader<R>> for AdjacencyList
 where
     R: Read,
@@ -150,9 +700,10 @@ where
 
     fn try_from(mut lines: LineReader<R>) -> Result<Self> {
         let header = lines.next_line().expect("missing header line")?;
+        let weighted = header == WEIGHTED_ADJACENCY_GRAPH_HEADER;
         ensure!(
-            header == b"AdjacencyGraph\n",
-            "Can only read AdjacencyGraph files but got {:?}",
+            weighted || header == ADJACENCY_GRAPH_HEADER,
+            "Can only read AdjacencyGraph or WeightedAdjacencyGraph files but got {:?}",
             std::str::from_utf8(header)
         );
 
@@ -164,6 +715,7 @@ where
 
         let mut offsets = Vec::with_capacity(node_count);
         let mut targets = Vec::with_capacity(rel_count);
+        let mut weights = Vec::with_capacity(if weighted { rel_count } else { 0 });
 
         let mut batch = lines.next_batch().expect("missing graph data")?;
 
@@ -191,7 +743,19 @@ where
             };
         }
 
-        Ok(Self::from((offsets, targets)))
+        while weights.capacity() > weights.len() {
+            match Weight::from_radix_10_signed(batch) {
+                (_, 0) => {
+                    batch = lines.next_batch().expect("missing weights")?;
+                }
+                (num, used) => {
+                    weights.push(num);
+                    batch = &batch[used + 1..];
+                }
+            };
+        }
+
+        Ok(Self::from((offsets, targets)).with_weights(weights))
     }
 }
 
@@ -207,7 +771,7 @@ impl From<(Vec<usize>, Vec<usize>)> for AdjacencyList {
         };
 
         let mut nodes = Vec::with_capacity(node_count);
-        for &[offset, next_offset] in offsets.array_windows::<2>() {
+        for (&offset, &next_offset) in offsets.iter().zip(&offsets[1..]) {
             let node = Node {
                 offset,
                 degree: next_offset - offset,
@@ -231,6 +795,7 @@ impl From<(Vec<usize>, Vec<usize>)> for AdjacencyList {
         AdjacencyList {
             nodes: nodes.into_boxed_slice(),
             targets: targets.into_boxed_slice(),
+            weights: Box::default(),
         }
     }
 }
@@ -243,20 +808,40 @@ impl From<AdjacencyList> for AdjacencyGraph {
 }
 
 impl AdjacencyList {
+    pub fn with_weights(self, weights: impl Into<Box<[Weight]>>) -> Self {
+        let weights = weights.into();
+        assert!(
+            weights.is_empty() || weights.len() == self.targets.len(),
+            "expected {} weights but got {}",
+            self.targets.len(),
+            weights.len()
+        );
+        Self { weights, ..self }
+    }
+
     pub fn invert(&self) -> Self {
+        if self.is_weighted() {
+            let (inverted, weights) = self.invert_with(&self.weights);
+            inverted.with_weights(weights)
+        } else {
+            self.invert_with(&vec![(); self.targets.len()]).0
+        }
+    }
+
+    /// Inverts the list and carries along `data`, which is aligned with the targets.
+    fn invert_with<T: Copy>(&self, data: &[T]) -> (Self, Vec<T>) {
         let node_count = self.nodes.len();
         let rel_count = self.targets.len();
 
         let mut temp = Vec::with_capacity(rel_count);
-        temp.resize(rel_count, (usize::max_value(), usize::max_value()));
 
         self.nodes
             .iter()
             .enumerate()
             .for_each(|(source, &Node { offset, degree })| {
                 let end = offset + degree;
-                for (&target, tmp) in self.targets[offset..end].iter().zip(&mut temp[offset..end]) {
-                    *tmp = (target, source);
+                for (&target, &data) in self.targets[offset..end].iter().zip(&data[offset..end]) {
+                    temp.push((target, source, data));
                 }
             });
 
@@ -277,51 +862,42 @@ impl AdjacencyList {
 
         // let mut temp = unsafe { Vec::from_raw_parts(temp as *mut (usize, usize), len, cap) };
 
-        temp.sort_by_key(|(target, _)| *target);
+        temp.sort_by_key(|(target, _, _)| *target);
 
         let mut offsets = Vec::with_capacity(node_count);
         let mut targets = Vec::with_capacity(rel_count);
+        let mut inverted_data = Vec::with_capacity(rel_count);
 
-        let mut last_target = usize::max_value();
+        let mut last_target = usize::MAX;
 
-        for (target, source) in temp.into_iter() {
+        for (target, source, data) in temp.into_iter() {
             while target != last_target {
                 offsets.push(targets.len());
                 last_target = last_target.wrapping_add(1);
             }
 
             targets.push(source);
+            inverted_data.push(data);
         }
 
-        offsets.extend(std::iter::repeat(targets.len()).take(node_count - last_target));
+        offsets.extend(std::iter::repeat_n(
+            targets.len(),
+            node_count - offsets.len(),
+        ));
 
-        Self::from((offsets, targets))
+        (Self::from((offsets, targets)), inverted_data)
     }
 }
 
 pub fn parse(input: PathBuf, output: PathBuf) -> Result<()> {
-    let start = Instant::now();
     let file = File::open(input)?;
     let output = File::create(output)?;
-
-    println!("preparing input: {:?}", start.elapsed());
-    let start = Instant::now();
-
     let adjacencies = AdjacencyList::try_from(LineReader::new(file))?;
-
-    println!("parsing input: {:?}", start.elapsed());
-    let start = Instant::now();
-
     let graph = AdjacencyGraph::from(adjacencies);
-
-    println!("building full graph: {:?}", start.elapsed());
-
     dump(graph, output)
 }
 
 pub fn dump(graph: AdjacencyGraph, mut output: impl Write) -> Result<()> {
-    let start = Instant::now();
-
     let node_count = graph.node_count();
     let rel_count = graph.rel_count();
     let meta = [node_count, rel_count];
@@ -332,215 +908,227 @@ pub fn dump(graph: AdjacencyGraph, mut output: impl Write) -> Result<()> {
     let AdjacencyList {
         nodes: out_nodes,
         targets: out_targets,
+        weights: out_weights,
     } = out;
 
-    let out_nodes = Box::into_raw(out_nodes) as *mut usize;
-    let out_nodes = unsafe { slice::from_raw_parts(out_nodes, node_count * 2) };
-
-    output.write_all(out_nodes.as_byte_slice())?;
+    output.write_all(as_bytes(&out_nodes))?;
     output.write_all(out_targets.as_byte_slice())?;
 
     let AdjacencyList {
         nodes: in_nodes,
         targets: in_targets,
+        weights: in_weights,
     } = inc;
 
-    let in_nodes = Box::into_raw(in_nodes) as *mut usize;
-    let in_nodes = unsafe { slice::from_raw_parts(in_nodes, node_count * 2) };
-
-    output.write_all(in_nodes.as_byte_slice())?;
+    output.write_all(as_bytes(&in_nodes))?;
     output.write_all(in_targets.as_byte_slice())?;
 
-    println!("serializing graph : {:?}", start.elapsed());
+    // weights are optional and appended at the end to keep unweighted files unchanged
+    output.write_all(out_weights.as_byte_slice())?;
+    output.write_all(in_weights.as_byte_slice())?;
 
     Ok(())
 }
 
-pub fn load_graph(input: PathBuf) -> Result<impl Graph + Sync> {
-    let start = Instant::now();
+/// An opened graph file, which the graphs returned from [`load_graph`] may borrow from.
+#[derive(Debug)]
+pub struct GraphInput {
+    #[cfg(feature = "mapped_graph")]
+    map: Mmap,
+    #[cfg(not(feature = "mapped_graph"))]
+    file: File,
+}
+
+pub fn open_graph(input: PathBuf) -> Result<GraphInput> {
     let file = File::open(input)?;
 
-    println!("preparing input: {:?}", start.elapsed());
-    let start = Instant::now();
+    #[cfg(feature = "mapped_graph")]
+    let input = GraphInput {
+        map: unsafe { Mmap::map(&file)? },
+    };
+    #[cfg(not(feature = "mapped_graph"))]
+    let input = GraphInput { file };
+
+    Ok(input)
+}
+
+pub fn load_graph(input: &GraphInput) -> Result<impl Graph + Sync + '_> {
+    #[cfg(feature = "mapped_graph")]
+    {
+        load_map(&input.map)
+    }
+
+    #[cfg(not(feature = "mapped_graph"))]
+    {
+        load(&input.file)
+    }
+}
 
+pub fn load_weighted_graph(input: &GraphInput) -> Result<impl WeightedGraph + Sync + '_> {
     let graph = {
         #[cfg(feature = "mapped_graph")]
         {
-            load_map(file)
+            load_map(&input.map)
         }
 
         #[cfg(not(feature = "mapped_graph"))]
         {
-            load(file)
+            load(&input.file)
         }
     }?;
 
-    println!("building full graph: {:?}", start.elapsed());
+    ensure!(
+        graph.is_weighted(),
+        "input graph has no relationship weights"
+    );
+
     Ok(graph)
 }
 
 #[cfg(feature = "mapped_graph")]
-pub fn load_map(input: File) -> Result<MappedGraph> {
-    let start = Instant::now();
-    let map = unsafe { Mmap::map(&input)? };
-
-    let (node_count_bytes, rest) = map.split_at(std::mem::size_of::<usize>());
-    let (rel_count_bytes, rest) = rest.split_at(std::mem::size_of::<usize>());
-    let node_count = usize::from_le_bytes(node_count_bytes.try_into().unwrap());
-    let rel_count = usize::from_le_bytes(rel_count_bytes.try_into().unwrap());
-
-    let (out_nodes_bytes, rest) = rest.split_at(node_count * std::mem::size_of::<Node>());
-    let (out_targets_bytes, rest) = rest.split_at(rel_count * std::mem::size_of::<usize>());
-
-    let (in_nodes_bytes, rest) = rest.split_at(node_count * std::mem::size_of::<Node>());
-    let (in_targets_bytes, rest) = rest.split_at(rel_count * std::mem::size_of::<usize>());
-
-    ensure!(rest.is_empty(), "extra data");
-
-    let out_nodes: &'static [Node] = unsafe { std::mem::transmute(out_nodes_bytes) };
-    let out_targets: &'static [usize] = unsafe { std::mem::transmute(out_targets_bytes) };
-
-    let in_nodes: &'static [Node] = unsafe { std::mem::transmute(in_nodes_bytes) };
-    let in_targets: &'static [usize] = unsafe { std::mem::transmute(in_targets_bytes) };
-
-    println!("deserializing graph : {:?}", start.elapsed());
-
-    Ok(MappedGraph {
-        map,
-        node_count,
-        rel_count,
-        out_nodes,
-        out_targets,
-        in_nodes,
-        in_targets,
-    })
+pub fn load_map(map: &Mmap) -> Result<MappedGraph<'_>> {
+    MappedGraph::new(map)
 }
 
 pub fn load(mut input: impl Read) -> Result<AdjacencyGraph> {
-    let start = Instant::now();
-
     let mut meta = [0_usize; 2];
     input.read_exact(meta.as_mut_byte_slice())?;
 
     let [node_count, rel_count] = meta;
 
-    let mut out_nodes = Box::<[Node]>::new_uninit_slice(node_count);
-    let out_nodes_ref = out_nodes.as_mut_ptr() as *mut usize;
-    let out_nodes_ref = unsafe { slice::from_raw_parts_mut(out_nodes_ref, node_count * 2) };
-    input.read_exact(out_nodes_ref.as_mut_byte_slice())?;
-
-    let out_targets = Box::<[usize]>::new_uninit_slice(rel_count);
-    let mut out_targets = unsafe { out_targets.assume_init() };
-    input.read_exact(out_targets.as_mut_byte_slice())?;
-
-    let mut in_nodes = Box::<[Node]>::new_uninit_slice(node_count);
-    let in_nodes_ref = in_nodes.as_mut_ptr() as *mut usize;
-    let in_nodes_ref = unsafe { slice::from_raw_parts_mut(in_nodes_ref, node_count * 2) };
-    input.read_exact(in_nodes_ref.as_mut_byte_slice())?;
-
-    let in_targets = Box::<[usize]>::new_uninit_slice(rel_count);
-    let mut in_targets = unsafe { in_targets.assume_init() };
-    input.read_exact(in_targets.as_mut_byte_slice())?;
+    let out_nodes = read_values::<Node>(&mut input, node_count)?;
+    let out_targets = read_values::<usize>(&mut input, rel_count)?;
+    let in_nodes = read_values::<Node>(&mut input, node_count)?;
+    let in_targets = read_values::<usize>(&mut input, rel_count)?;
+
+    // only an input that ends right before the weights is unweighted,
+    // a partial weights section is an error like any other truncated section
+    let (out_weights, in_weights) = match read_byte(&mut input)? {
+        Some(first) if rel_count > 0 => {
+            let first = [first];
+            let mut input = first.as_slice().chain(input);
+            let out_weights = read_values::<Weight>(&mut input, rel_count)?;
+            let in_weights = read_values::<Weight>(&mut input, rel_count)?;
+            (out_weights, in_weights)
+        }
+        _ => (Box::default(), Box::default()),
+    };
 
     let out = AdjacencyList {
-        nodes: unsafe { out_nodes.assume_init() },
+        nodes: out_nodes,
         targets: out_targets,
+        weights: out_weights,
     };
     let inc = AdjacencyList {
-        nodes: unsafe { in_nodes.assume_init() },
+        nodes: in_nodes,
         targets: in_targets,
+        weights: in_weights,
     };
 
-    println!("deserializing graph : {:?}", start.elapsed());
-
     Ok(AdjacencyGraph { out, inc })
 }
 
-#[cfg(test)]
-pub(crate) mod tests {
-    use super::*;
-
-    pub trait FilterGraph: Graph {
-        type Delegate: Graph;
-
-        fn delegate(&self) -> &Self::Delegate;
-
-        fn node_count(&self) -> usize {
-            Graph::node_count(self.delegate())
-        }
-
-        fn rel_count(&self) -> usize {
-            Graph::rel_count(self.delegate())
-        }
-
-        fn out(&self, node: usize) -> &[usize] {
-            Graph::out(self.delegate(), node)
-        }
-
-        fn inc(&self, node: usize) -> &[usize] {
-            Graph::inc(self.delegate(), node)
-        }
-
-        fn out_degree(&self, node: usize) -> usize {
-            Graph::out_degree(self.delegate(), node)
-        }
-
-        fn inc_degree(&self, node: usize) -> usize {
-            Graph::inc_degree(self.delegate(), node)
-        }
-
-        fn threshold(&self) -> usize {
-            Graph::threshold(self.delegate())
+/// Reads a single byte, or `None` if the input has ended.
+fn read_byte(input: &mut impl Read) -> std::io::Result<Option<u8>> {
+    let mut byte = [0_u8];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
         }
     }
+}
 
-    impl<T> Graph for T
-    where
-        T: FilterGraph,
-    {
-        fn node_count(&self) -> usize {
-            FilterGraph::node_count(self)
-        }
+/// Reads `len` values that were written as their in-memory representation.
+#[cfg(not(feature = "nightly"))]
+fn read_values<T: Pod>(input: &mut impl Read, len: usize) -> std::io::Result<Box<[T]>> {
+    use std::alloc::{alloc_zeroed, handle_alloc_error, Layout};
 
-        fn rel_count(&self) -> usize {
-            FilterGraph::rel_count(self)
-        }
+    let layout = Layout::array::<T>(len).expect("too many values to allocate");
+    if layout.size() == 0 {
+        return Ok(Vec::new().into_boxed_slice());
+    }
 
-        fn out(&self, node: usize) -> &[usize] {
-            FilterGraph::out(self, node)
-        }
+    // large zeroed allocations are served by fresh pages of the OS,
+    // so we do not pay for zeroing memory that is overwritten right away
+    let values = unsafe { alloc_zeroed(layout) } as *mut T;
+    if values.is_null() {
+        handle_alloc_error(layout);
+    }
+    // SAFETY: the allocation has the layout of `len` values and all zeroes are a valid `T`
+    let mut values = unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(values, len)) };
 
-        fn inc(&self, node: usize) -> &[usize] {
-            FilterGraph::inc(self, node)
-        }
+    // SAFETY: `T` is plain data, so any bytes that are read are a valid `T`
+    let bytes = unsafe { slice::from_raw_parts_mut(values.as_mut_ptr() as *mut u8, layout.size()) };
+    input.read_exact(bytes)?;
+    Ok(values)
+}
 
-        fn out_degree(&self, node: usize) -> usize {
-            FilterGraph::out_degree(self, node)
-        }
+/// Reads `len` values that were written as their in-memory representation.
+#[cfg(feature = "nightly")]
+fn read_values<T: Pod>(input: &mut impl Read, len: usize) -> std::io::Result<Box<[T]>> {
+    use std::{io::BorrowedBuf, mem::MaybeUninit};
+
+    // reads straight into uninitialized memory, without zeroing it first
+    let mut values = Box::<[T]>::new_uninit_slice(len);
+    let bytes = unsafe {
+        slice::from_raw_parts_mut(
+            values.as_mut_ptr() as *mut MaybeUninit<u8>,
+            std::mem::size_of::<T>() * len,
+        )
+    };
+    let mut buf = BorrowedBuf::from(bytes);
+    input.read_buf_exact(buf.unfilled())?;
+    // SAFETY: all bytes were filled and `T` is plain data
+    Ok(unsafe { values.assume_init() })
+}
 
-        fn inc_degree(&self, node: usize) -> usize {
-            FilterGraph::inc_degree(self, node)
-        }
-    }
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
 
     #[derive(Default)]
     pub(crate) struct MockGraph {
         out: Vec<Vec<usize>>,
         inc: Vec<Vec<usize>>,
+        out_weights: Vec<Vec<Weight>>,
+        inc_weights: Vec<Vec<Weight>>,
     }
 
     impl MockGraph {
         pub fn new(out: Vec<Vec<usize>>) -> Self {
+            let out = out
+                .into_iter()
+                .map(|targets| targets.into_iter().map(|target| (target, 1)).collect())
+                .collect();
+            Self::weighted(out)
+        }
+
+        pub fn weighted(out: Vec<Vec<(usize, Weight)>>) -> Self {
             let mut inc = vec![];
+            let mut inc_weights = vec![];
             for (source, targets) in out.iter().enumerate() {
-                for target in targets.iter() {
-                    if *target >= inc.len() {
+                for &(target, weight) in targets.iter() {
+                    if target >= inc.len() {
                         inc.resize_with(target + 1, Vec::new);
+                        inc_weights.resize_with(target + 1, Vec::new);
                     }
-                    inc[*target].push(source)
+                    inc[target].push(source);
+                    inc_weights[target].push(weight);
                 }
             }
-            MockGraph { out, inc }
+            let (out, out_weights) = out
+                .into_iter()
+                .map(|targets| targets.into_iter().unzip())
+                .unzip();
+            MockGraph {
+                out,
+                inc,
+                out_weights,
+                inc_weights,
+            }
         }
     }
 
@@ -576,6 +1164,248 @@ pub(crate) mod tests {
         }
     }
 
+    impl WeightedGraph for MockGraph {
+        fn out_weights(&self, node: usize) -> &[Weight] {
+            match self.out_weights.get(node) {
+                Some(weights) => weights.as_slice(),
+                None => &[],
+            }
+        }
+
+        fn inc_weights(&self, node: usize) -> &[Weight] {
+            match self.inc_weights.get(node) {
+                Some(weights) => weights.as_slice(),
+                None => &[],
+            }
+        }
+    }
+
     #[test]
     fn adjacency_graph() {}
+
+    #[test]
+    fn adjacency_list_invert() {
+        // 0 -> 1, nodes 0 and 2 have no incoming relationships
+        let list = AdjacencyList::from((vec![0, 1, 1], vec![1]));
+        let inverted = list.invert();
+        assert_eq!(inverted.nodes.len(), 3);
+        assert_eq!(inverted.rels(0), &[] as &[usize]);
+        assert_eq!(inverted.rels(1), &[0]);
+        assert_eq!(inverted.rels(2), &[] as &[usize]);
+
+        let inverted = AdjacencyList::from((vec![0, 0], vec![])).invert();
+        assert_eq!(inverted.nodes.len(), 2);
+    }
+
+    #[test]
+    fn transposed_graph() {
+        // 0 -(3)-> 1, 0 -(5)-> 2, 1 -(7)-> 2
+        let graph = MockGraph::weighted(vec![vec![(1, 3), (2, 5)], vec![(2, 7)], vec![]]);
+        let transposed = Transposed::new(graph);
+
+        assert_eq!(transposed.node_count(), 3);
+        assert_eq!(transposed.rel_count(), 3);
+        assert_eq!(transposed.out(2), &[0, 1]);
+        assert_eq!(transposed.out_weights(2), &[5, 7]);
+        assert_eq!(transposed.out_degree(0), 0);
+        assert_eq!(transposed.inc(0), &[1, 2]);
+        assert_eq!(transposed.inc_weights(0), &[3, 5]);
+        assert_eq!(transposed.inc_degree(0), 2);
+    }
+
+    #[test]
+    fn filtered_graph() {
+        // 0 -(3)-> 1, 0 -(5)-> 2, 1 -(7)-> 2, 2 -(1)-> 3
+        let graph = MockGraph::weighted(vec![
+            vec![(1, 3), (2, 5)],
+            vec![(2, 7)],
+            vec![(3, 1)],
+            vec![],
+        ]);
+        let filtered = FilteredGraph::new(graph)
+            .with_node_filter(|node| node != 1)
+            .with_relationship_filter(|source, target| (source, target) != (0, 3))
+            .with_weight_above(2);
+
+        assert_eq!(filtered.node_count(), 4);
+        assert_eq!(filtered.out(0), &[1, 2]);
+        assert!(!filtered.contains_out(0, 0));
+        assert!(filtered.contains_out(0, 1));
+        assert!(!filtered.contains_out(1, 0));
+        assert!(!filtered.contains_out(2, 0));
+
+        assert_eq!(filtered.inc(2), &[0, 1]);
+        assert!(filtered.contains_inc(2, 0));
+        assert!(!filtered.contains_inc(2, 1));
+        assert!(!filtered.contains_inc(3, 0));
+
+        // only 0 -(5)-> 2 is visible
+        assert_eq!(filtered.rel_count(), 1);
+        assert_eq!(filtered.out_degree(0), 1);
+        assert_eq!(filtered.out_degree(1), 0);
+        assert_eq!(filtered.out_degree(2), 0);
+        assert_eq!(filtered.inc_degree(2), 1);
+        assert_eq!(filtered.inc_degree(3), 0);
+
+        let transposed = Transposed::new(filtered);
+        assert!(transposed.contains_out(2, 0));
+        assert!(!transposed.contains_out(2, 1));
+        assert_eq!(transposed.out_degree(2), 1);
+    }
+
+    #[test]
+    fn adjacency_graph_from_filtered_graph() {
+        let graph = MockGraph::new(vec![vec![1, 2], vec![2], vec![3], vec![]]);
+        let filtered = FilteredGraph::new(graph).with_node_filter(|node| node != 1);
+
+        let graph = AdjacencyGraph::from_graph(&filtered);
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.rel_count(), 2);
+        assert_eq!(graph.out(0), &[2]);
+        assert_eq!(graph.out(1), &[] as &[usize]);
+        assert_eq!(graph.inc(2), &[0]);
+        assert_eq!(graph.inc(3), &[2]);
+    }
+
+    #[test]
+    fn weighted_adjacency_graph() {
+        // 0 -(4)-> 1, 0 -(-2)-> 3, 2 -(7)-> 1
+        let input: &[u8] = b"WeightedAdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n4\n-2\n7\n";
+        let list = AdjacencyList::try_from(LineReader::new(input)).unwrap();
+        let graph = AdjacencyGraph::from(list);
+
+        assert!(graph.is_weighted());
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.out(0), &[1, 3]);
+        assert_eq!(graph.out_weights(0), &[4, -2]);
+        assert_eq!(graph.out_weights(2), &[7]);
+        assert_eq!(graph.inc(1), &[0, 2]);
+        assert_eq!(graph.inc_weights(1), &[4, 7]);
+        assert_eq!(graph.inc(3), &[0]);
+        assert_eq!(graph.inc_weights(3), &[-2]);
+        assert_eq!(graph.inc_degree(0), 0);
+    }
+
+    #[test]
+    fn dump_and_load() {
+        for input in [
+            &b"AdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n"[..],
+            &b"WeightedAdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n4\n-2\n7\n"[..],
+        ] {
+            let list = AdjacencyList::try_from(LineReader::new(input)).unwrap();
+            let mut bytes = Vec::new();
+            dump(AdjacencyGraph::from(list), &mut bytes).unwrap();
+
+            let graph = load(bytes.as_slice()).unwrap();
+            let expected =
+                AdjacencyGraph::from(AdjacencyList::try_from(LineReader::new(input)).unwrap());
+
+            assert_eq!(graph.node_count(), expected.node_count());
+            assert_eq!(graph.rel_count(), expected.rel_count());
+            assert_eq!(graph.is_weighted(), expected.is_weighted());
+            for node in 0..graph.node_count() {
+                assert_eq!(graph.out(node), expected.out(node));
+                assert_eq!(graph.inc(node), expected.inc(node));
+                if graph.is_weighted() {
+                    assert_eq!(graph.out_weights(node), expected.out_weights(node));
+                    assert_eq!(graph.inc_weights(node), expected.inc_weights(node));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn load_truncated_weights() {
+        let input = b"WeightedAdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n4\n-2\n7\n";
+        let list = AdjacencyList::try_from(LineReader::new(&input[..])).unwrap();
+        let mut bytes = Vec::new();
+        dump(AdjacencyGraph::from(list), &mut bytes).unwrap();
+
+        let weights_len = 2 * 3 * std::mem::size_of::<Weight>();
+        let unweighted = &bytes[..bytes.len() - weights_len];
+        assert!(!load(unweighted).unwrap().is_weighted());
+
+        for len in [1, weights_len / 2, weights_len - 1] {
+            let truncated = &bytes[..bytes.len() - weights_len + len];
+            assert!(load(truncated).is_err(), "loaded {} bytes of weights", len);
+        }
+    }
+
+    /// The dumped bytes of `input`, aligned like a memory mapping.
+    #[cfg(feature = "mapped_graph")]
+    fn dumped(input: &[u8]) -> Vec<usize> {
+        let list = AdjacencyList::try_from(LineReader::new(input)).unwrap();
+        let mut bytes = Vec::new();
+        dump(AdjacencyGraph::from(list), &mut bytes).unwrap();
+        let mut words = vec![0_usize; bytes.len() / std::mem::size_of::<usize>()];
+        words.as_mut_byte_slice().copy_from_slice(&bytes);
+        words
+    }
+
+    #[test]
+    #[cfg(feature = "mapped_graph")]
+    fn mapped_graph() {
+        let words = dumped(b"WeightedAdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n4\n-2\n7\n");
+        let bytes = as_bytes(&words);
+
+        let graph = MappedGraph::new(bytes).unwrap();
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.rel_count(), 3);
+        assert!(graph.is_weighted());
+        assert_eq!(graph.out(0), &[1, 3]);
+        assert_eq!(graph.out_weights(0), &[4, -2]);
+        assert_eq!(graph.inc(1), &[0, 2]);
+        assert_eq!(graph.inc_degree(3), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "mapped_graph")]
+    fn mapped_graph_truncated() {
+        for input in [
+            &b"AdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n"[..],
+            &b"WeightedAdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n4\n-2\n7\n"[..],
+        ] {
+            let words = dumped(input);
+            let bytes = as_bytes(&words);
+            assert!(MappedGraph::new(bytes).is_ok());
+            for len in (0..bytes.len()).filter(|len| len % 8 != 0 || *len < bytes.len() - 48) {
+                assert!(MappedGraph::new(&bytes[..len]).is_err(), "len {}", len);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "mapped_graph")]
+    fn mapped_graph_extra_data() {
+        let mut words = dumped(b"WeightedAdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n4\n-2\n7\n");
+        words.push(42);
+        assert!(MappedGraph::new(as_bytes(&words)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mapped_graph")]
+    fn mapped_graph_misaligned() {
+        let words = dumped(b"AdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n");
+        let mut shifted = vec![0_usize; words.len() + 1];
+        shifted.as_mut_byte_slice()[1..=words.len() * 8].copy_from_slice(as_bytes(&words));
+        let bytes = &as_bytes(&shifted)[1..=words.len() * 8];
+        assert!(MappedGraph::new(bytes).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mapped_graph")]
+    fn mapped_graph_corrupt() {
+        let words = dumped(b"AdjacencyGraph\n4\n3\n0\n2\n2\n3\n1\n3\n1\n");
+        assert!(MappedGraph::new(as_bytes(&words)).is_ok());
+        // the header, then (degree, offset) of the outgoing nodes and the outgoing targets
+        for (index, value) in [(3, 2), (2, 4), (2, usize::MAX), (10, 4), (17, 7)] {
+            let mut corrupt = words.clone();
+            corrupt[index] = value;
+            assert!(
+                MappedGraph::new(as_bytes(&corrupt)).is_err(),
+                "word {}",
+                index
+            );
+        }
+    }
 }
@@ -0,0 +1,22 @@
+//! Ligra in rust: a lightweight graph processing framework for shared memory.
+//!
+//! - [`graph`] loads and represents graphs
+//! - [`ligra`] is the engine that maps over relationships and nodes of a frontier
+//! - [`algos`] contains the algorithms that are built on the engine
+
+#![cfg_attr(feature = "nightly", feature(read_buf, core_io_borrowed_buf))]
+
+#[cfg(test)]
+extern crate quickcheck;
+#[cfg(test)]
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
+
+#[macro_use]
+extern crate eyre;
+
+pub use eyre::Result;
+
+pub mod algos;
+pub mod graph;
+pub mod ligra;
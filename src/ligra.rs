@@ -1,10 +1,27 @@
-use crate::graph::Graph;
-pub use node_set::NodeSubset;
+use crate::graph::{Graph, Weight, WeightedGraph};
+pub use buckets::Buckets;
+pub use node_set::{NodeSubset, NodeSubsetData};
 use rayon::prelude::*;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "sparse_atomic_pack")]
+use std::sync::atomic::AtomicUsize;
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+pub use trace::{Direction, Operation, Trace, TraceEvent};
+pub use workspace::Workspace;
 
+#[path = "buckets.rs"]
+mod buckets;
 #[path = "node_set.rs"]
 mod node_set;
+#[path = "primitives.rs"]
+pub mod primitives;
+#[path = "trace.rs"]
+mod trace;
+#[path = "workspace.rs"]
+mod workspace;
 
 pub fn par_vec<T: Send>(len: usize, f: impl Fn(usize) -> T + Send + Sync) -> Vec<T> {
     let mut data = Vec::with_capacity(len);
@@ -31,13 +48,9 @@ pub fn par_vec_copy<T: Clone + Send + Sync>(len: usize, initial_value: T) -> Vec
     data
 }
 
-pub trait RelationshipMapper {
-    fn update(&self, source: usize, target: usize) -> bool;
-
-    fn update_non_atomic(&self, source: usize, target: usize) -> bool {
-        self.update(source, target)
-    }
-
+/// How the engine checks the targets and collects the output of a mapper,
+/// shared by [`RelationshipMapper`] and [`WeightedRelationshipMapper`].
+pub trait MapperFlags {
     fn check(&self, _target: usize) -> bool {
         true
     }
@@ -53,6 +66,30 @@ pub trait RelationshipMapper {
     fn has_no_result(&self) -> bool {
         self.update_always_returns_true()
     }
+
+    /// Whether the sparse output must not contain the same target more than once.
+    ///
+    /// Only needed when `update` can return true for a target from more than one source.
+    fn remove_duplicates(&self) -> bool {
+        false
+    }
+}
+
+pub trait RelationshipMapper: MapperFlags {
+    fn update(&self, source: usize, target: usize) -> bool;
+
+    fn update_non_atomic(&self, source: usize, target: usize) -> bool {
+        self.update(source, target)
+    }
+}
+
+/// Like [`RelationshipMapper`], but `update` also receives the weight of the relationship.
+pub trait WeightedRelationshipMapper: MapperFlags {
+    fn update(&self, source: usize, target: usize, weight: Weight) -> bool;
+
+    fn update_non_atomic(&self, source: usize, target: usize, weight: Weight) -> bool {
+        self.update(source, target, weight)
+    }
 }
 
 pub fn relationship_map<G, T>(graph: &G, node_subset: &mut NodeSubset, mapper: &T)
@@ -60,185 +97,675 @@ where
     G: Graph + Sync + ?Sized,
     T: RelationshipMapper + Sync + ?Sized,
 {
-    let subset_size = node_subset.subset_count();
-
-    let degrees = if node_subset.is_dense() {
-        par_vec(node_subset.node_count(), |node_id| {
-            if node_subset.contains(node_id) {
-                graph.out_degree(node_id)
-            } else {
-                0
-            }
-        })
-    } else {
-        par_vec(subset_size, |i| {
-            let node_id = node_subset.node(i);
-            graph.out_degree(node_id)
-        })
-    };
+    relationship_map_with(graph, node_subset, mapper, &mut Workspace::default())
+}
 
-    let out_degrees = degrees.par_iter().sum::<usize>();
+/// Like [`relationship_map`], but reuses the buffers of `workspace`.
+pub fn relationship_map_with<G, T>(
+    graph: &G,
+    node_subset: &mut NodeSubset,
+    mapper: &T,
+    workspace: &mut Workspace,
+) where
+    G: Graph + Sync + ?Sized,
+    T: RelationshipMapper + Sync + ?Sized,
+{
+    edge_map(&Unweighted { graph, mapper }, node_subset, workspace)
+}
 
-    if out_degrees > graph.threshold() {
-        node_subset.to_dense();
-        relationship_map_dense(graph, node_subset, mapper)
-    } else {
-        relationship_map_sparse(graph, node_subset, degrees, mapper)
-    }
+pub fn weighted_relationship_map<G, T>(graph: &G, node_subset: &mut NodeSubset, mapper: &T)
+where
+    G: WeightedGraph + Sync + ?Sized,
+    T: WeightedRelationshipMapper + Sync + ?Sized,
+{
+    weighted_relationship_map_with(graph, node_subset, mapper, &mut Workspace::default())
 }
 
-fn relationship_map_sparse<G, T>(
+/// Like [`weighted_relationship_map`], but reuses the buffers of `workspace`.
+pub fn weighted_relationship_map_with<G, T>(
     graph: &G,
     node_subset: &mut NodeSubset,
-    degrees: Vec<usize>,
     mapper: &T,
+    workspace: &mut Workspace,
 ) where
+    G: WeightedGraph + Sync + ?Sized,
+    T: WeightedRelationshipMapper + Sync + ?Sized,
+{
+    edge_map(&Weighted { graph, mapper }, node_subset, workspace)
+}
+
+// A note on memory orderings: all atomics written during a traversal are only read
+// by other threads of the same traversal for their value, or after the parallel
+// iterator has finished. Rayon's join synchronizes with everything that happened
+// in the joined tasks, so `Relaxed` is sufficient throughout the engine.
+
+/// Binds a graph to a mapper so that the sparse and dense traversals
+/// can be shared between unweighted and weighted relationship maps.
+trait Relationships: Sync {
+    fn node_count(&self) -> usize;
+
+    fn threshold(&self) -> usize;
+
+    fn out_degree(&self, node: usize) -> usize;
+
+    /// The length of `out(node)`, which includes the relationships that are not visible.
+    fn out_len(&self, node: usize) -> usize;
+
+    fn check(&self, target: usize) -> bool;
+
+    fn has_no_result(&self) -> bool;
+
+    fn remove_duplicates(&self) -> bool;
+
+    /// Updates the outgoing relationships of `source` at the positions in `range`
+    /// and calls `on_update` with the target of every successful update.
+    fn push<F>(&self, source: usize, range: Range<usize>, on_update: F)
+    where
+        F: FnMut(usize);
+
+    /// Updates the incoming relationships of `target` that start in `node_subset`
+    /// and returns whether any update succeeded and how many relationships were looked at.
+    fn pull(&self, target: usize, node_subset: &NodeSubset) -> (bool, usize);
+}
+
+struct Unweighted<'a, G: ?Sized, T: ?Sized> {
+    graph: &'a G,
+    mapper: &'a T,
+}
+
+impl<'a, G, T> Relationships for Unweighted<'a, G, T>
+where
     G: Graph + Sync + ?Sized,
     T: RelationshipMapper + Sync + ?Sized,
 {
-    if mapper.has_no_result() {
-        node_subset.nodes().par_iter().for_each(|&source| {
-            graph.out(source).par_iter().for_each(|&target| {
-                if mapper.check(target) {
-                    mapper.update(source, target);
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    fn threshold(&self) -> usize {
+        self.graph.threshold()
+    }
+
+    fn out_degree(&self, node: usize) -> usize {
+        self.graph.out_degree(node)
+    }
+
+    fn out_len(&self, node: usize) -> usize {
+        self.graph.out(node).len()
+    }
+
+    fn check(&self, target: usize) -> bool {
+        self.mapper.check(target)
+    }
+
+    fn has_no_result(&self) -> bool {
+        self.mapper.has_no_result()
+    }
+
+    fn remove_duplicates(&self) -> bool {
+        self.mapper.remove_duplicates()
+    }
+
+    fn push<F>(&self, source: usize, range: Range<usize>, mut on_update: F)
+    where
+        F: FnMut(usize),
+    {
+        let targets = &self.graph.out(source)[range.clone()];
+        for (j, &target) in range.zip(targets) {
+            if self.graph.contains_out(source, j)
+                && self.mapper.check(target)
+                && self.mapper.update(source, target)
+            {
+                on_update(target)
+            }
+        }
+    }
+
+    fn pull(&self, target: usize, node_subset: &NodeSubset) -> (bool, usize) {
+        let sources = self.graph.inc(target);
+        let mut updated = false;
+        for (j, &source) in sources.iter().enumerate() {
+            if node_subset.contains(source)
+                && self.graph.contains_inc(target, j)
+                && self.mapper.update(source, target)
+            {
+                updated = true;
+            }
+            if !self.mapper.check(target) {
+                return (updated, j + 1);
+            }
+        }
+        (updated, sources.len())
+    }
+}
+
+struct Weighted<'a, G: ?Sized, T: ?Sized> {
+    graph: &'a G,
+    mapper: &'a T,
+}
+
+impl<'a, G, T> Relationships for Weighted<'a, G, T>
+where
+    G: WeightedGraph + Sync + ?Sized,
+    T: WeightedRelationshipMapper + Sync + ?Sized,
+{
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    fn threshold(&self) -> usize {
+        self.graph.threshold()
+    }
+
+    fn out_degree(&self, node: usize) -> usize {
+        self.graph.out_degree(node)
+    }
+
+    fn out_len(&self, node: usize) -> usize {
+        self.graph.out(node).len()
+    }
+
+    fn check(&self, target: usize) -> bool {
+        self.mapper.check(target)
+    }
+
+    fn has_no_result(&self) -> bool {
+        self.mapper.has_no_result()
+    }
+
+    fn remove_duplicates(&self) -> bool {
+        self.mapper.remove_duplicates()
+    }
+
+    fn push<F>(&self, source: usize, range: Range<usize>, mut on_update: F)
+    where
+        F: FnMut(usize),
+    {
+        let targets = &self.graph.out(source)[range.clone()];
+        let weights = &self.graph.out_weights(source)[range.clone()];
+        for (j, (&target, &weight)) in range.zip(targets.iter().zip(weights)) {
+            if self.graph.contains_out(source, j)
+                && self.mapper.check(target)
+                && self.mapper.update(source, target, weight)
+            {
+                on_update(target)
+            }
+        }
+    }
+
+    fn pull(&self, target: usize, node_subset: &NodeSubset) -> (bool, usize) {
+        let mut updated = false;
+        let sources = self.graph.inc(target);
+        let weights = self.graph.inc_weights(target);
+        for (j, (&source, &weight)) in sources.iter().zip(weights).enumerate() {
+            if node_subset.contains(source)
+                && self.graph.contains_inc(target, j)
+                && self.mapper.update(source, target, weight)
+            {
+                updated = true;
+            }
+            if !self.mapper.check(target) {
+                return (updated, j + 1);
+            }
+        }
+        (updated, sources.len())
+    }
+}
+
+fn edge_map<R>(rels: &R, node_subset: &mut NodeSubset, workspace: &mut Workspace)
+where
+    R: Relationships + ?Sized,
+{
+    let start = Instant::now();
+    let subset_size = node_subset.subset_count();
+
+    // the direction is chosen by the visible degrees, while the sparse traversal
+    // needs the positions of the visible relationships in the full lists
+    let (mut degrees, out_degrees) = if node_subset.is_dense() {
+        let mut degrees = workspace.take_degrees(node_subset.node_count());
+        (0..node_subset.node_count())
+            .into_par_iter()
+            .map(|node_id| {
+                if node_subset.contains(node_id) {
+                    rels.out_len(node_id)
+                } else {
+                    0
                 }
             })
+            .collect_into_vec(&mut degrees);
+        let out_degrees = (0..node_subset.node_count())
+            .into_par_iter()
+            .filter(|&node_id| node_subset.contains(node_id))
+            .map(|node_id| rels.out_degree(node_id))
+            .sum::<usize>();
+        (degrees, out_degrees)
+    } else {
+        let mut degrees = workspace.take_degrees(subset_size);
+        node_subset
+            .nodes()
+            .par_iter()
+            .map(|&node_id| rels.out_len(node_id))
+            .collect_into_vec(&mut degrees);
+        let out_degrees = node_subset
+            .nodes()
+            .par_iter()
+            .map(|&node_id| rels.out_degree(node_id))
+            .sum::<usize>();
+        (degrees, out_degrees)
+    };
+
+    let (direction, traversed) = if out_degrees > rels.threshold() {
+        node_subset.to_dense_with(workspace);
+        let traversed = relationship_map_dense(rels, node_subset, workspace);
+        (Direction::Dense, traversed)
+    } else {
+        if node_subset.is_dense() {
+            node_subset.to_sparse_with(workspace);
+            node_subset
+                .nodes()
+                .par_iter()
+                .map(|&node_id| rels.out_len(node_id))
+                .collect_into_vec(&mut degrees);
+        }
+        let traversed = relationship_map_sparse(rels, node_subset, &mut degrees, workspace);
+        (Direction::Sparse, traversed)
+    };
+
+    workspace.give_degrees(degrees);
+
+    if let Some(trace) = workspace.trace_mut() {
+        trace.push(TraceEvent {
+            operation: Operation::RelationshipMap,
+            iteration: trace.iterations(Operation::RelationshipMap),
+            direction,
+            frontier_size: subset_size,
+            out_degrees: Some(out_degrees),
+            relationships_traversed: Some(traversed),
+            elapsed: start.elapsed(),
+        });
+    }
+}
+
+/// Number of relationships that are traversed by a single task in sparse mode.
+const EDGE_BLOCK_SIZE: usize = 4096;
+
+fn relationship_map_sparse<R>(
+    rels: &R,
+    node_subset: &mut NodeSubset,
+    degrees: &mut [usize],
+    workspace: &mut Workspace,
+) -> usize
+where
+    R: Relationships + ?Sized,
+{
+    let out_rel_count = primitives::scan(degrees);
+    let offsets = degrees;
+
+    if rels.has_no_result() {
+        edge_blocks(out_rel_count).for_each(|block| {
+            push_block(
+                rels,
+                node_subset.nodes(),
+                offsets,
+                out_rel_count,
+                block,
+                |_| {},
+            )
         });
     } else {
-        *node_subset = relationship_map_sparse_output(graph, node_subset, degrees, mapper);
+        let mut next =
+            relationship_map_sparse_output(rels, node_subset, offsets, out_rel_count, workspace);
+        if rels.remove_duplicates() {
+            remove_duplicates(&mut next, rels.node_count(), workspace);
+        }
+        let next = NodeSubset::sparse(node_subset.node_count(), next);
+        workspace.recycle(std::mem::replace(node_subset, next));
+    }
+    out_rel_count
+}
+
+/// Keeps only the first occurrence of every node in `nodes`.
+///
+/// Uses a flag per node that is only reset for the nodes that were set,
+/// so the cost is proportional to the output and not to the node count.
+fn remove_duplicates(nodes: &mut Vec<usize>, node_count: usize, workspace: &mut Workspace) {
+    let seen = workspace.take_seen(node_count);
+    let seen = unsafe { std::mem::transmute::<Vec<bool>, Vec<AtomicBool>>(seen) };
+
+    let mut keep = workspace.take_dense_empty(nodes.len());
+    nodes
+        .par_iter()
+        .map(|&node| !seen[node].swap(true, Ordering::Relaxed))
+        .collect_into_vec(&mut keep);
+    nodes
+        .par_iter()
+        .for_each(|&node| seen[node].store(false, Ordering::Relaxed));
+
+    let seen = unsafe { std::mem::transmute::<Vec<AtomicBool>, Vec<bool>>(seen) };
+    workspace.give_seen(seen);
+
+    let mut unique = workspace.take_sparse(nodes.len());
+    primitives::pack_into(nodes, &keep, &mut unique);
+    workspace.give_dense(keep);
+    workspace.give_sparse(std::mem::replace(nodes, unique));
+}
+
+/// Splits the relationships of the frontier into blocks of (almost) equal size,
+/// so that a single high degree node is split across multiple tasks
+/// and many low degree nodes are handled by a single task.
+fn edge_blocks(out_rel_count: usize) -> impl IndexedParallelIterator<Item = Range<usize>> {
+    let block_count = out_rel_count.div_ceil(EDGE_BLOCK_SIZE);
+    (0..block_count).into_par_iter().map(move |block| {
+        let start = block * EDGE_BLOCK_SIZE;
+        start..out_rel_count.min(start + EDGE_BLOCK_SIZE)
+    })
+}
+
+/// Pushes all relationships in `block`, which indexes into the concatenated
+/// out relationships of `nodes`, whose first positions are given by `offsets`.
+fn push_block<R, F>(
+    rels: &R,
+    nodes: &[usize],
+    offsets: &[usize],
+    out_rel_count: usize,
+    block: Range<usize>,
+    mut on_update: F,
+) where
+    R: Relationships + ?Sized,
+    F: FnMut(usize),
+{
+    // the last node that starts at or before the block, nodes without relationships are skipped
+    let mut i = offsets.partition_point(|&offset| offset <= block.start) - 1;
+    let mut position = block.start;
+    while position < block.end {
+        let node_start = offsets[i];
+        let node_end = offsets.get(i + 1).copied().unwrap_or(out_rel_count);
+        let end = node_end.min(block.end);
+        rels.push(
+            nodes[i],
+            (position - node_start)..(end - node_start),
+            &mut on_update,
+        );
+        position = end;
+        i += 1;
     }
 }
 
 #[cfg(feature = "sparse_atomic_pack")]
-fn relationship_map_sparse_output<G, T>(
-    graph: &G,
+fn relationship_map_sparse_output<R>(
+    rels: &R,
     node_subset: &NodeSubset,
-    degrees: Vec<usize>,
-    mapper: &T,
-) -> NodeSubset
+    offsets: &[usize],
+    out_rel_count: usize,
+    workspace: &mut Workspace,
+) -> Vec<usize>
 where
-    G: Graph + Sync + ?Sized,
-    T: RelationshipMapper + Sync + ?Sized,
+    R: Relationships + ?Sized,
 {
-    let out_rel_count = degrees.into_par_iter().sum::<usize>();
-    let out_rels = par_vec_with(out_rel_count, || AtomicUsize::new(usize::MAX));
+    let mut out_rels = workspace.take_sparse(out_rel_count);
+    out_rels.resize(out_rel_count, usize::MAX);
+    let out_rels = unsafe { std::mem::transmute::<Vec<usize>, Vec<AtomicUsize>>(out_rels) };
 
     let write_idx = AtomicUsize::default();
-    node_subset.nodes().par_iter().for_each(|&source| {
-        graph.out(source).par_iter().for_each(|&target| {
-            if mapper.check(target) && mapper.update(source, target) {
-                let idx = write_idx.fetch_add(1, Ordering::SeqCst);
-                out_rels[idx].store(target, Ordering::SeqCst);
-            }
-        })
+    edge_blocks(out_rel_count).for_each(|block| {
+        push_block(
+            rels,
+            node_subset.nodes(),
+            offsets,
+            out_rel_count,
+            block,
+            |target| {
+                let idx = write_idx.fetch_add(1, Ordering::Relaxed);
+                out_rels[idx].store(target, Ordering::Relaxed);
+            },
+        )
     });
 
     let mut out_rels = unsafe { std::mem::transmute::<Vec<AtomicUsize>, Vec<usize>>(out_rels) };
-    let write_idx = write_idx.load(Ordering::SeqCst);
+    let write_idx = write_idx.load(Ordering::Relaxed);
 
     out_rels.truncate(write_idx);
-
-    NodeSubset::sparse(node_subset.node_count(), out_rels)
+    out_rels
 }
 
 #[cfg(not(feature = "sparse_atomic_pack"))]
-fn relationship_map_sparse_output<G, T>(
-    graph: &G,
+fn relationship_map_sparse_output<R>(
+    rels: &R,
     node_subset: &NodeSubset,
-    mut degrees: Vec<usize>,
-    mapper: &T,
-) -> NodeSubset
+    offsets: &[usize],
+    out_rel_count: usize,
+    workspace: &mut Workspace,
+) -> Vec<usize>
 where
-    G: Graph + Sync + ?Sized,
-    T: RelationshipMapper + Sync + ?Sized,
+    R: Relationships + ?Sized,
 {
-    // before [1 3 3  7]
-    // after  [1 4 7 14]
-    let out_rel_count = degrees
+    // every block collects its updated targets locally, so the memory
+    // we need is proportional to the output and not to the traversed relationships
+    let block_count = out_rel_count.div_ceil(EDGE_BLOCK_SIZE);
+    let mut blocks = workspace.take_blocks(block_count);
+    blocks[..block_count]
         .par_iter_mut()
-        .fold_with(0_usize, |sum, degree| {
-            *degree += sum;
-            *degree
-        })
-        .sum::<usize>();
+        .zip(edge_blocks(out_rel_count))
+        .for_each(|(targets, block)| {
+            push_block(
+                rels,
+                node_subset.nodes(),
+                offsets,
+                out_rel_count,
+                block,
+                |target| targets.push(target),
+            );
+        });
 
-    let offsets = degrees;
+    let blocks_len = blocks[..block_count].iter().map(Vec::len).sum();
+    let mut out_rels = workspace.take_sparse(blocks_len);
+    primitives::flatten_into(&blocks[..block_count], &mut out_rels);
+    workspace.give_blocks(blocks);
+
+    out_rels
+}
+
+/// Returns the number of relationships that were looked at.
+fn relationship_map_dense<R>(
+    rels: &R,
+    node_subset: &mut NodeSubset,
+    workspace: &mut Workspace,
+) -> usize
+where
+    R: Relationships + ?Sized,
+{
+    let node_count = rels.node_count();
+
+    if rels.has_no_result() {
+        (0..node_count)
+            .into_par_iter()
+            .filter(|&target| rels.check(target))
+            .map(|target| rels.pull(target, node_subset).1)
+            .sum()
+    } else {
+        let next = workspace.take_dense(node_count);
+        let next = unsafe { std::mem::transmute::<Vec<bool>, Vec<AtomicBool>>(next) };
 
-    let out_rels = par_vec_with(out_rel_count, || AtomicUsize::new(usize::MAX));
+        let traversed = (0..node_count)
+            .into_par_iter()
+            .filter(|&target| rels.check(target))
+            .map(|target| {
+                let (updated, traversed) = rels.pull(target, node_subset);
+                if updated {
+                    next[target].store(true, Ordering::Relaxed);
+                }
+                traversed
+            })
+            .sum();
+
+        let next = unsafe { std::mem::transmute::<Vec<AtomicBool>, Vec<bool>>(next) };
+        let next = NodeSubset::dense(node_count, next);
+        workspace.recycle(std::mem::replace(node_subset, next));
+        traversed
+    }
+}
 
-    node_subset
+/// Aggregates the values of all relationships from the frontier into their targets.
+///
+/// `reduce` must be associative and commutative, the order in which
+/// relationships are combined is unspecified.
+pub trait RelationshipReducer {
+    type Value: Copy + PartialEq + Send + Sync;
+
+    /// The neutral element of `reduce`, targets that end up with it are not reported.
+    fn identity(&self) -> Self::Value;
+
+    fn map(&self, source: usize, target: usize) -> Self::Value;
+
+    fn reduce(&self, left: Self::Value, right: Self::Value) -> Self::Value;
+
+    fn check(&self, _target: usize) -> bool {
+        true
+    }
+}
+
+/// Reduces the relationships leaving `node_subset` per target and returns
+/// all targets with a non-identity result together with that result.
+///
+/// The sparse direction semisorts the (target, value) pairs of the frontier,
+/// the dense direction pulls over the incoming relationships of every target.
+pub fn relationship_map_reduce<G, T>(
+    graph: &G,
+    node_subset: &mut NodeSubset,
+    reducer: &T,
+) -> NodeSubsetData<T::Value>
+where
+    G: Graph + Sync + ?Sized,
+    T: RelationshipReducer + Sync + ?Sized,
+{
+    relationship_map_reduce_with(graph, node_subset, reducer, &mut Workspace::default())
+}
+
+/// Like [`relationship_map_reduce`], but converts the subset with the buffers of `workspace`
+/// and records the call in its trace.
+pub fn relationship_map_reduce_with<G, T>(
+    graph: &G,
+    node_subset: &mut NodeSubset,
+    reducer: &T,
+    workspace: &mut Workspace,
+) -> NodeSubsetData<T::Value>
+where
+    G: Graph + Sync + ?Sized,
+    T: RelationshipReducer + Sync + ?Sized,
+{
+    let start = Instant::now();
+    let subset_size = node_subset.subset_count();
+
+    let out_degrees = if node_subset.is_dense() {
+        (0..node_subset.node_count())
+            .into_par_iter()
+            .filter(|&node_id| node_subset.contains(node_id))
+            .map(|node_id| graph.out_degree(node_id))
+            .sum::<usize>()
+    } else {
+        node_subset
+            .nodes()
+            .par_iter()
+            .map(|&node_id| graph.out_degree(node_id))
+            .sum::<usize>()
+    };
+
+    let (direction, reduced) = if out_degrees > graph.threshold() {
+        node_subset.to_dense_with(workspace);
+        let reduced = relationship_map_reduce_dense(graph, node_subset, reducer);
+        (Direction::Dense, reduced)
+    } else {
+        node_subset.to_sparse_with(workspace);
+        let reduced = relationship_map_reduce_sparse(graph, node_subset, reducer);
+        (Direction::Sparse, reduced)
+    };
+
+    if let Some(trace) = workspace.trace_mut() {
+        // the dense direction looks at all incoming relationships of the checked targets
+        let traversed = match direction {
+            Direction::Sparse => out_degrees,
+            Direction::Dense => (0..graph.node_count())
+                .into_par_iter()
+                .filter(|&target| reducer.check(target))
+                .map(|target| graph.inc_degree(target))
+                .sum(),
+        };
+        trace.push(TraceEvent {
+            operation: Operation::RelationshipMapReduce,
+            iteration: trace.iterations(Operation::RelationshipMapReduce),
+            direction,
+            frontier_size: subset_size,
+            out_degrees: Some(out_degrees),
+            relationships_traversed: Some(traversed),
+            elapsed: start.elapsed(),
+        });
+    }
+
+    reduced
+}
+
+fn relationship_map_reduce_sparse<G, T>(
+    graph: &G,
+    node_subset: &NodeSubset,
+    reducer: &T,
+) -> NodeSubsetData<T::Value>
+where
+    G: Graph + Sync + ?Sized,
+    T: RelationshipReducer + Sync + ?Sized,
+{
+    let pairs = node_subset
         .nodes()
         .par_iter()
-        .zip(offsets.into_par_iter())
-        .for_each(|(node_id, offset)| {
-            let source = *node_id;
+        .flat_map_iter(|&source| {
             graph
                 .out(source)
-                .par_iter()
+                .iter()
                 .enumerate()
-                .for_each(|(j, &target)| {
-                    if mapper.check(target) && mapper.update(source, target) {
-                        out_rels[offset + j].store(target, Ordering::SeqCst)
-                    }
-                })
-        });
-
-    let mut out_rels = unsafe { std::mem::transmute::<Vec<AtomicUsize>, Vec<usize>>(out_rels) };
-    let mut write_idx = 0;
-
-    // pack non-max values together
-    for i in 0..out_rels.len() {
-        let target = out_rels[i];
-        if target != usize::MAX {
-            out_rels[write_idx] = target;
-            write_idx += 1;
-        }
-    }
+                .filter(move |&(j, &target)| graph.contains_out(source, j) && reducer.check(target))
+                .map(move |(_, &target)| (target, reducer.map(source, target)))
+        })
+        .collect::<Vec<_>>();
 
-    out_rels.truncate(write_idx);
+    let identity = reducer.identity();
+    let mut reduced = primitives::reduce_by_key(&pairs, |left, right| reducer.reduce(left, right));
+    reduced.retain(|&(_, value)| value != identity);
 
-    NodeSubset::sparse(node_subset.node_count(), out_rels)
+    NodeSubsetData::new(node_subset.node_count(), reduced)
 }
 
-fn relationship_map_dense<G, T>(graph: &G, node_subset: &mut NodeSubset, mapper: &T)
+fn relationship_map_reduce_dense<G, T>(
+    graph: &G,
+    node_subset: &NodeSubset,
+    reducer: &T,
+) -> NodeSubsetData<T::Value>
 where
     G: Graph + Sync + ?Sized,
-    T: RelationshipMapper + Sync + ?Sized,
+    T: RelationshipReducer + Sync + ?Sized,
 {
-    let node_count = graph.node_count();
-
-    if mapper.has_no_result() {
-        (0..node_count).into_par_iter().for_each(|target| {
-            if mapper.check(target) {
-                for &source in graph.inc(target) {
-                    if node_subset.contains(source) {
-                        mapper.update(source, target);
-                    }
-                    if !mapper.check(target) {
-                        break;
-                    }
-                }
-            }
-        });
-    } else {
-        let next = par_vec_with(node_count, AtomicBool::default);
-
-        (0..node_count).into_par_iter().for_each(|target| {
-            if mapper.check(target) {
-                for &source in graph.inc(target) {
-                    if node_subset.contains(source) && mapper.update(source, target) {
-                        next[target].store(true, Ordering::SeqCst);
-                    }
-                    if !mapper.check(target) {
-                        break;
-                    }
-                }
+    let identity = reducer.identity();
+    let reduced = (0..graph.node_count())
+        .into_par_iter()
+        .filter(|&target| reducer.check(target))
+        .filter_map(|target| {
+            let value = graph
+                .inc(target)
+                .iter()
+                .enumerate()
+                .filter(|&(j, &source)| {
+                    node_subset.contains(source) && graph.contains_inc(target, j)
+                })
+                .fold(identity, |acc, (_, &source)| {
+                    reducer.reduce(acc, reducer.map(source, target))
+                });
+            if value != identity {
+                Some((target, value))
+            } else {
+                None
             }
-        });
+        })
+        .collect::<Vec<_>>();
 
-        let next = unsafe { std::mem::transmute::<Vec<AtomicBool>, Vec<bool>>(next) };
-        *node_subset = NodeSubset::dense(node_count, next);
-    }
+    NodeSubsetData::new(node_subset.node_count(), reduced)
 }
 
 pub trait NodeMapper {
@@ -269,45 +796,65 @@ pub fn node_filter<T: NodeMapper + Sync + ?Sized>(
     node_subset: &NodeSubset,
     mapper: &T,
 ) -> NodeSubset {
+    node_filter_with(node_subset, mapper, &mut Workspace::default())
+}
+
+/// Like [`node_filter`], but takes the buffer of the result from `workspace`.
+///
+/// There is no `node_map_with`, as [`node_map`] does not allocate.
+pub fn node_filter_with<T: NodeMapper + Sync + ?Sized>(
+    node_subset: &NodeSubset,
+    mapper: &T,
+    workspace: &mut Workspace,
+) -> NodeSubset {
+    let start = Instant::now();
     let node_count = node_subset.node_count();
     let subset_count = node_subset.subset_count();
 
-    if node_subset.is_dense() {
-        let dense = par_vec(node_count, |node| {
-            node_subset.contains(node) && mapper.update(node)
-        });
+    let next = if node_subset.is_dense() {
+        let mut dense = workspace.take_dense_empty(node_count);
+        (0..node_count)
+            .into_par_iter()
+            .map(|node| node_subset.contains(node) && mapper.update(node))
+            .collect_into_vec(&mut dense);
         NodeSubset::dense(node_count, dense)
     } else {
-        let mut sparse = Vec::with_capacity(subset_count);
+        let mut keep = workspace.take_dense_empty(subset_count);
         node_subset
             .nodes()
             .par_iter()
-            .map(|&node| {
-                if mapper.update(node) {
-                    node
-                } else {
-                    usize::MAX
-                }
-            })
-            .collect_into_vec(&mut sparse);
-
-        let mut write_index = 0;
-        for read_index in 0..subset_count {
-            let value = sparse[read_index];
-            if value != usize::MAX {
-                sparse[write_index] = value;
-                write_index += 1;
-            }
-        }
+            .map(|&node| mapper.update(node))
+            .collect_into_vec(&mut keep);
+
+        let mut sparse = workspace.take_sparse(subset_count);
+        primitives::pack_into(node_subset.nodes(), &keep, &mut sparse);
+        workspace.give_dense(keep);
+
+        NodeSubset::sparse(node_count, sparse)
+    };
 
-        sparse.truncate(write_index);
-        NodeSubset::sparse_counted(node_count, write_index, sparse)
+    if let Some(trace) = workspace.trace_mut() {
+        trace.push(TraceEvent {
+            operation: Operation::NodeFilter,
+            iteration: trace.iterations(Operation::NodeFilter),
+            direction: if node_subset.is_dense() {
+                Direction::Dense
+            } else {
+                Direction::Sparse
+            },
+            frontier_size: subset_count,
+            out_degrees: None,
+            relationships_traversed: None,
+            elapsed: start.elapsed(),
+        });
     }
+
+    next
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::graph::{self, tests::MockGraph};
+    use crate::graph::{tests::MockGraph, FilteredGraph};
     use quickcheck::Arbitrary;
 
     use super::*;
@@ -347,6 +894,16 @@ mod tests {
         }
     }
 
+    impl<F: Fn(usize, usize) -> bool + Send + Sync> MapperFlags for FnMapper<F> {}
+
+    struct FnNodeMapper<F: Fn(usize) -> bool + Send + Sync>(F);
+
+    impl<F: Fn(usize) -> bool + Send + Sync> NodeMapper for FnNodeMapper<F> {
+        fn update(&self, node: usize) -> bool {
+            self.0(node)
+        }
+    }
+
     #[test]
     fn test_relationship_map() {
         // 0 -> 1 -> 2 -> 3
@@ -368,4 +925,296 @@ mod tests {
         node_subset.to_sparse();
         assert!(dbg!(node_subset.nodes()).eq(vec![2].as_slice()));
     }
+
+    #[test]
+    fn test_relationship_map_sparse() {
+        // 0 -> 1, 0 -> 2 and a disconnected hub that raises the threshold to 3
+        let input = vec![vec![1, 2], vec![], vec![], vec![3; 60]];
+        let graph = MockGraph::new(input);
+        let mut node_subset = NodeSubset::single(graph.node_count(), 0);
+
+        relationship_map(&graph, &mut node_subset, &FnMapper(|_, target| target == 2));
+
+        assert!(!node_subset.is_dense());
+        assert_eq!(node_subset.nodes(), &[2]);
+    }
+
+    #[test]
+    fn test_relationship_map_dense_to_sparse() {
+        // 0 -> 1 and a disconnected hub that raises the threshold to 3
+        let input = vec![vec![1], vec![], vec![3; 60], vec![]];
+        let graph = MockGraph::new(input);
+        let mut node_subset = NodeSubset::single(graph.node_count(), 0);
+        node_subset.to_dense();
+
+        relationship_map(&graph, &mut node_subset, &FnMapper(|_, _| true));
+
+        assert!(!node_subset.is_dense());
+        assert_eq!(node_subset.nodes(), &[1]);
+    }
+
+    struct WeightedFnMapper<F: Fn(usize, usize, Weight) -> bool + Send + Sync>(F);
+
+    impl<F: Fn(usize, usize, Weight) -> bool + Send + Sync> WeightedRelationshipMapper
+        for WeightedFnMapper<F>
+    {
+        fn update(&self, source: usize, target: usize, weight: Weight) -> bool {
+            self.0(source, target, weight)
+        }
+    }
+
+    impl<F: Fn(usize, usize, Weight) -> bool + Send + Sync> MapperFlags for WeightedFnMapper<F> {}
+
+    #[test]
+    fn test_weighted_relationship_map() {
+        // 0 -(3)-> 1, 0 -(5)-> 2, 1 -(7)-> 2
+        let input = vec![vec![(1, 3), (2, 5)], vec![(2, 7)], vec![]];
+        let mapper = WeightedFnMapper(|source, target, weight| {
+            assert_eq!(source, 0);
+            assert_eq!(weight, if target == 1 { 3 } else { 5 });
+            target == 2
+        });
+
+        // dense: the frontier out degree exceeds the threshold of 0
+        let graph = MockGraph::weighted(input.clone());
+        let mut node_subset = NodeSubset::single(graph.node_count(), 0);
+        weighted_relationship_map(&graph, &mut node_subset, &mapper);
+        assert!(node_subset.is_dense());
+        node_subset.to_sparse();
+        assert_eq!(node_subset.nodes(), &[2]);
+
+        // sparse: a disconnected hub raises the threshold to 3
+        let mut input = input;
+        input.push(vec![(3, 1); 60]);
+        let graph = MockGraph::weighted(input);
+        let mut node_subset = NodeSubset::single(graph.node_count(), 0);
+        weighted_relationship_map(&graph, &mut node_subset, &mapper);
+        assert!(!node_subset.is_dense());
+        assert_eq!(node_subset.nodes(), &[2]);
+    }
+
+    struct CountFrontierNeighbors;
+
+    impl RelationshipReducer for CountFrontierNeighbors {
+        type Value = usize;
+
+        fn identity(&self) -> usize {
+            0
+        }
+
+        fn map(&self, _source: usize, _target: usize) -> usize {
+            1
+        }
+
+        fn reduce(&self, left: usize, right: usize) -> usize {
+            left + right
+        }
+
+        fn check(&self, target: usize) -> bool {
+            target != 4
+        }
+    }
+
+    #[test]
+    fn test_relationship_map_reduce() {
+        // 0 -> {2, 3, 4}, 1 -> {2, 4}, 2 -> {3}
+        let mut input = vec![vec![2, 3, 4], vec![2, 4], vec![3], vec![], vec![]];
+
+        // dense: the frontier out degree exceeds the threshold of 0
+        let graph = MockGraph::new(input.clone());
+        let mut node_subset = NodeSubset::sparse(graph.node_count(), vec![0, 1]);
+        let counts = relationship_map_reduce(&graph, &mut node_subset, &CountFrontierNeighbors);
+        assert!(node_subset.is_dense());
+        let mut counts = counts.iter().map(|(n, c)| (n, *c)).collect::<Vec<_>>();
+        counts.sort_unstable();
+        assert_eq!(counts, vec![(2, 2), (3, 1)]);
+
+        // sparse: a disconnected hub raises the threshold to 5
+        input.push(vec![5; 100]);
+        let graph = MockGraph::new(input);
+        let mut node_subset = NodeSubset::sparse(graph.node_count(), vec![0, 1]);
+        let mut workspace = Workspace::traced();
+        let counts = relationship_map_reduce_with(
+            &graph,
+            &mut node_subset,
+            &CountFrontierNeighbors,
+            &mut workspace,
+        );
+        assert!(!node_subset.is_dense());
+        let mut counts = counts.iter().map(|(n, c)| (n, *c)).collect::<Vec<_>>();
+        counts.sort_unstable();
+        assert_eq!(counts, vec![(2, 2), (3, 1)]);
+
+        let events = workspace.trace().unwrap().events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].operation, Operation::RelationshipMapReduce);
+        assert_eq!(events[0].direction, Direction::Sparse);
+        assert_eq!(events[0].out_degrees, Some(5));
+    }
+
+    #[test]
+    fn test_relationship_map_filtered() {
+        // 0 -> 1 -> 2, 0 -> 2, 3 -> 3 (x100)
+        let input = vec![vec![1, 2], vec![2], vec![], vec![3; 100]];
+        let graph = FilteredGraph::new(MockGraph::new(input)).with_node_filter(|node| node != 1);
+        let mapper = FnMapper(|source, target| {
+            assert_eq!((source, target), (0, 2));
+            true
+        });
+
+        // sparse
+        let mut node_subset = NodeSubset::single(graph.node_count(), 0);
+        relationship_map(&graph, &mut node_subset, &mapper);
+        assert_eq!(node_subset.nodes(), &[2]);
+
+        // dense
+        let mut node_subset = NodeSubset::sparse(graph.node_count(), vec![0, 3]);
+        relationship_map(
+            &graph,
+            &mut node_subset,
+            &FnMapper(|source, target| source == 0 && target == 2),
+        );
+        assert!(node_subset.is_dense());
+        node_subset.to_sparse();
+        assert_eq!(node_subset.nodes(), &[2]);
+    }
+
+    #[test]
+    fn test_relationship_map_sparse_blocks() {
+        // 0 and 2 have no relationships, 1 -> 3..10003 spans multiple blocks
+        let mut input = vec![vec![], (3..10_003).collect(), vec![]];
+        input.resize(10_003, vec![]);
+        // a disconnected hub keeps the traversal sparse
+        input.push(vec![10_003; 200_000]);
+        let graph = MockGraph::new(input);
+
+        let mut node_subset = NodeSubset::sparse(graph.node_count(), vec![0, 1, 2]);
+        relationship_map(
+            &graph,
+            &mut node_subset,
+            &FnMapper(|_, target| target % 2 == 0),
+        );
+
+        assert!(!node_subset.is_dense());
+        let mut actual = node_subset.nodes().to_vec();
+        actual.sort_unstable();
+        let expected = (3..10_003).filter(|t| t % 2 == 0).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    struct UniqueFnMapper<F: Fn(usize, usize) -> bool + Send + Sync>(F);
+
+    impl<F: Fn(usize, usize) -> bool + Send + Sync> RelationshipMapper for UniqueFnMapper<F> {
+        fn update(&self, source: usize, target: usize) -> bool {
+            self.0(source, target)
+        }
+    }
+
+    impl<F: Fn(usize, usize) -> bool + Send + Sync> MapperFlags for UniqueFnMapper<F> {
+        fn remove_duplicates(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_relationship_map_remove_duplicates() {
+        // 0, 1 and 2 all point to 3 and 4, 2 also points to itself
+        let mut input = vec![vec![3, 4], vec![4, 3], vec![2, 3, 4], vec![], vec![]];
+        // a disconnected hub keeps the traversal sparse
+        input.push(vec![5; 1000]);
+        let graph = MockGraph::new(input);
+
+        let mut node_subset = NodeSubset::sparse(graph.node_count(), vec![0, 1, 2]);
+        relationship_map(&graph, &mut node_subset, &FnMapper(|_, _| true));
+        assert_eq!(node_subset.subset_count(), 7);
+
+        let mut workspace = Workspace::new();
+        for _ in 0..2 {
+            let mut node_subset = NodeSubset::sparse(graph.node_count(), vec![0, 1, 2]);
+            relationship_map_with(
+                &graph,
+                &mut node_subset,
+                &UniqueFnMapper(|_, _| true),
+                &mut workspace,
+            );
+
+            assert!(!node_subset.is_dense());
+            let mut actual = node_subset.nodes().to_vec();
+            actual.sort_unstable();
+            assert_eq!(actual, vec![2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn test_workspace_trace() {
+        // 0 -> 1 -> 2, 0 -> 2 and a disconnected hub
+        let mut input = vec![vec![1, 2], vec![2], vec![], vec![]];
+        input.push(vec![3; 100]);
+        let graph = MockGraph::new(input);
+
+        let mut workspace = Workspace::traced();
+        let mut frontier = NodeSubset::single(graph.node_count(), 0);
+        relationship_map_with(
+            &graph,
+            &mut frontier,
+            &FnMapper(|_, _| true),
+            &mut workspace,
+        );
+        let _ = node_filter_with(&frontier, &FnNodeMapper(|_| true), &mut workspace);
+        let mut frontier = NodeSubset::single(graph.node_count(), 4);
+        relationship_map_with(
+            &graph,
+            &mut frontier,
+            &FnMapper(|_, _| true),
+            &mut workspace,
+        );
+
+        let trace = workspace.trace().unwrap();
+        let events = trace.events();
+        assert_eq!(events.len(), 3);
+        assert_eq!(trace.iterations(Operation::RelationshipMap), 2);
+
+        assert_eq!(events[0].operation, Operation::RelationshipMap);
+        assert_eq!(events[0].iteration, 0);
+        assert_eq!(events[0].direction, Direction::Sparse);
+        assert_eq!(events[0].frontier_size, 1);
+        assert_eq!(events[0].out_degrees, Some(2));
+        assert_eq!(events[0].relationships_traversed, Some(2));
+
+        assert_eq!(events[1].operation, Operation::NodeFilter);
+        assert_eq!(events[1].iteration, 0);
+        assert_eq!(events[1].frontier_size, 2);
+        assert_eq!(events[1].out_degrees, None);
+
+        // the dense traversal looks at the incoming relationships of every target
+        assert_eq!(events[2].iteration, 1);
+        assert_eq!(events[2].direction, Direction::Dense);
+        assert_eq!(events[2].out_degrees, Some(100));
+        assert_eq!(events[2].relationships_traversed, Some(103));
+
+        assert!(Workspace::new().trace().is_none());
+    }
+
+    #[test]
+    fn test_workspace_reuses_buffers() {
+        // a ring of 100 nodes and a disconnected hub that keeps the traversals sparse
+        let mut input = (0..100).map(|n| vec![(n + 1) % 100]).collect::<Vec<_>>();
+        input.push(vec![100; 10_000]);
+        let graph = MockGraph::new(input);
+        let mapper = FnMapper(|_, _| true);
+
+        let mut workspace = Workspace::new();
+        let mut frontier = NodeSubset::single(graph.node_count(), 0);
+        let mut allocations = Vec::new();
+        for _ in 0..10 {
+            let next = node_filter_with(&frontier, &FnNodeMapper(|_| true), &mut workspace);
+            workspace.recycle(std::mem::replace(&mut frontier, next));
+            relationship_map_with(&graph, &mut frontier, &mapper, &mut workspace);
+            allocations.push(workspace.allocations());
+        }
+
+        assert_eq!(frontier.nodes(), &[10]);
+        assert!(allocations[0] > 0);
+        assert_eq!(allocations[1], allocations[9]);
+    }
 }
@@ -1,25 +1,15 @@
-use rayon::iter::IndexedParallelIterator;
+use super::{primitives, Workspace};
+use rayon::prelude::*;
 
+#[derive(Default, Clone)]
 pub struct NodeSubset {
     node_count: usize,
     subset_count: usize,
-    dense: Option<Box<[bool]>>,
-    sparse: Option<Box<[usize]>>,
+    dense: Option<Vec<bool>>,
+    sparse: Option<Vec<usize>>,
     is_dense: bool,
 }
 
-impl Default for NodeSubset {
-    fn default() -> Self {
-        Self {
-            node_count: 0,
-            subset_count: 0,
-            dense: None,
-            sparse: None,
-            is_dense: false,
-        }
-    }
-}
-
 /// Constructors
 impl NodeSubset {
     pub fn empty(node_count: usize) -> Self {
@@ -43,7 +33,7 @@ impl NodeSubset {
         Self::dense_counted(node_count, node_count, dense)
     }
 
-    pub fn sparse(node_count: usize, sparse: impl Into<Box<[usize]>>) -> Self {
+    pub fn sparse(node_count: usize, sparse: impl Into<Vec<usize>>) -> Self {
         let sparse = sparse.into();
         Self::sparse_counted(node_count, sparse.len(), sparse)
     }
@@ -51,7 +41,7 @@ impl NodeSubset {
     pub fn sparse_counted(
         node_count: usize,
         subset_count: usize,
-        sparse: impl Into<Box<[usize]>>,
+        sparse: impl Into<Vec<usize>>,
     ) -> Self {
         Self {
             node_count,
@@ -62,16 +52,16 @@ impl NodeSubset {
         }
     }
 
-    pub fn dense(node_count: usize, dense: impl Into<Box<[bool]>>) -> Self {
+    pub fn dense(node_count: usize, dense: impl Into<Vec<bool>>) -> Self {
         let dense = dense.into();
-        let rel_count = dense.iter().filter(|d| **d).count();
+        let rel_count = dense.par_iter().filter(|d| **d).count();
         Self::dense_counted(node_count, rel_count, dense)
     }
 
     pub fn dense_counted(
         node_count: usize,
         subset_count: usize,
-        dense: impl Into<Box<[bool]>>,
+        dense: impl Into<Vec<bool>>,
     ) -> Self {
         Self {
             node_count,
@@ -121,14 +111,21 @@ impl NodeSubset {
     }
 
     pub fn to_dense(&mut self) {
+        self.to_dense_with(&mut Workspace::default())
+    }
+
+    /// Like [`NodeSubset::to_dense`], but takes the dense buffer from
+    /// and returns the sparse buffer to `workspace`.
+    pub fn to_dense_with(&mut self, workspace: &mut Workspace) {
         if self.dense.is_none() {
-            let mut dense = vec![false; self.node_count];
+            let mut dense = workspace.take_dense(self.node_count);
             if let Some(sparse) = self.sparse.take() {
-                for node in sparse.to_vec() {
+                for node in sparse.iter().copied() {
                     dense[node] = true;
                 }
+                workspace.give_sparse(sparse);
             }
-            self.dense = Some(dense.into_boxed_slice());
+            self.dense = Some(dense);
         }
         self.is_dense = true;
     }
@@ -143,20 +140,32 @@ impl NodeSubset {
     }
 
     pub fn to_sparse(&mut self) {
-        if self.sparse.is_none() && self.subset_count > 0 {
-            let mut sparse = Vec::with_capacity(self.subset_count);
+        self.to_sparse_with(&mut Workspace::default())
+    }
+
+    /// Like [`NodeSubset::to_sparse`], but takes the sparse buffer from
+    /// and returns the dense buffer to `workspace`.
+    pub fn to_sparse_with(&mut self, workspace: &mut Workspace) {
+        if self.sparse.is_none() {
+            let mut sparse = workspace.take_sparse(self.subset_count);
             if let Some(dense) = self.dense.take() {
-                for (node, _) in dense.to_vec().into_iter().enumerate().filter(|(_, d)| *d) {
-                    sparse.push(node);
-                }
+                primitives::pack_index_into(&dense, &mut sparse);
+                workspace.give_dense(dense);
             }
             assert_eq!(sparse.len(), self.subset_count);
-            self.sparse = Some(sparse.into_boxed_slice());
+            self.sparse = Some(sparse);
         }
         self.is_dense = false;
     }
 }
 
+/// Buffer recycling
+impl NodeSubset {
+    pub(crate) fn into_buffers(self) -> (Option<Vec<bool>>, Option<Vec<usize>>) {
+        (self.dense, self.sparse)
+    }
+}
+
 impl IntoIterator for NodeSubset {
     type Item = usize;
 
@@ -164,10 +173,10 @@ impl IntoIterator for NodeSubset {
 
     fn into_iter(self) -> Self::IntoIter {
         assert!(
-            self.is_dense == false,
+            !self.is_dense,
             "Dense NodeSubset does not support into_iter()"
         );
-        self.sparse.unwrap_or_default().into_vec().into_iter()
+        self.sparse.unwrap_or_default().into_iter()
     }
 }
 
@@ -178,17 +187,68 @@ impl<'a> IntoIterator for &'a NodeSubset {
 
     fn into_iter(self) -> Self::IntoIter {
         assert!(
-            self.is_dense == false,
+            !self.is_dense,
             "Dense NodeSubset does not support into_iter()"
         );
         let sparse = match &self.sparse {
-            Some(sparse) => sparse.as_ref(),
+            Some(sparse) => sparse.as_slice(),
             None => &[],
         };
         sparse.iter()
     }
 }
 
+/// A sparse node subset that carries a value for every contained node.
+#[derive(Debug, Clone)]
+pub struct NodeSubsetData<V> {
+    node_count: usize,
+    nodes: Box<[usize]>,
+    values: Box<[V]>,
+}
+
+impl<V: Send> NodeSubsetData<V> {
+    pub fn new(node_count: usize, entries: Vec<(usize, V)>) -> Self {
+        let (nodes, values): (Vec<_>, Vec<_>) = entries.into_par_iter().unzip();
+        Self {
+            node_count,
+            nodes: nodes.into_boxed_slice(),
+            values: values.into_boxed_slice(),
+        }
+    }
+}
+
+impl<V> NodeSubsetData<V> {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn subset_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn nodes(&self) -> &[usize] {
+        &self.nodes
+    }
+
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &V)> {
+        self.nodes.iter().copied().zip(self.values.iter())
+    }
+}
+
+impl<V> From<NodeSubsetData<V>> for NodeSubset {
+    fn from(data: NodeSubsetData<V>) -> Self {
+        NodeSubset::sparse(data.node_count, data.nodes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,7 +332,7 @@ mod tests {
         let node_subset = NodeSubset::empty(42);
         assert!(node_subset.is_empty());
         let node_subset = NodeSubset::single(42, 1337);
-        assert!(node_subset.is_empty() == false)
+        assert!(!node_subset.is_empty())
     }
 
     #[test]
@@ -280,7 +340,7 @@ mod tests {
         let mut node_subset = NodeSubset::full(42);
         assert!(node_subset.is_dense());
         node_subset.to_sparse();
-        assert!(node_subset.is_dense() == false);
+        assert!(!node_subset.is_dense());
     }
 
     #[test]
@@ -323,10 +383,10 @@ mod tests {
         assert!(node_subset.contains(3));
         assert!(node_subset.contains(4));
         assert!(node_subset.contains(7));
-        assert!(node_subset.contains(0) == false);
-        assert!(node_subset.contains(2) == false);
-        assert!(node_subset.contains(5) == false);
-        assert!(node_subset.contains(6) == false);
+        assert!(!node_subset.contains(0));
+        assert!(!node_subset.contains(2));
+        assert!(!node_subset.contains(5));
+        assert!(!node_subset.contains(6));
     }
 
     #[test]
@@ -355,6 +415,20 @@ mod tests {
         assert!(nodes.iter().eq(node_subset.iter()));
     }
 
+    #[test]
+    fn node_subset_data() {
+        let data = NodeSubsetData::new(42, vec![(1, 'a'), (9, 'b'), (8, 'c')]);
+        assert_eq!(data.node_count(), 42);
+        assert_eq!(data.subset_count(), 3);
+        assert_eq!(data.nodes(), &[1, 9, 8]);
+        assert_eq!(data.values(), &['a', 'b', 'c']);
+        assert!(data.iter().eq(vec![(1, &'a'), (9, &'b'), (8, &'c')]));
+
+        let node_subset = NodeSubset::from(data);
+        assert_eq!(node_subset.subset_count(), 3);
+        assert_eq!(node_subset.nodes(), &[1, 9, 8]);
+    }
+
     // panicking stuff
 
     #[test]
@@ -389,13 +463,13 @@ mod tests {
     #[should_panic(expected = "Dense NodeSubset does not support into_iter()")]
     fn dense_into_iter() {
         let node_subset = NodeSubset::full(42);
-        node_subset.into_iter();
+        let _ = node_subset.into_iter();
     }
 
     #[test]
     #[should_panic(expected = "Dense NodeSubset does not support into_iter()")]
     fn dense_into_iter_ref() {
         let node_subset = NodeSubset::full(42);
-        (&node_subset).into_iter();
+        let _ = (&node_subset).into_iter();
     }
 }
@@ -0,0 +1,213 @@
+use eyre::Result;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// How per-node results are written to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A `node,value` header followed by one `node,value` line per node.
+    Csv,
+    /// One value per line, the line number is the node id.
+    Text,
+    /// The values as a raw little-endian array of 8 byte integers or floats, without a header.
+    Binary,
+}
+
+impl OutputFormat {
+    /// Guesses the format from the extension of `path`, defaults to [`OutputFormat::Text`].
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => OutputFormat::Csv,
+            Some("bin") => OutputFormat::Binary,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(OutputFormat::Csv),
+            "text" => Ok(OutputFormat::Text),
+            "binary" => Ok(OutputFormat::Binary),
+            _ => bail!(
+                "invalid output format {:?}, use either csv, text or binary",
+                s
+            ),
+        }
+    }
+}
+
+/// A per-node result value that can be written in every [`OutputFormat`].
+pub trait OutputValue: Copy {
+    fn write_text<W: Write>(self, out: &mut W) -> std::io::Result<()>;
+
+    fn to_le_bytes(self) -> [u8; 8];
+}
+
+impl OutputValue for usize {
+    fn write_text<W: Write>(self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "{}", self)
+    }
+
+    fn to_le_bytes(self) -> [u8; 8] {
+        (self as u64).to_le_bytes()
+    }
+}
+
+impl OutputValue for f64 {
+    fn write_text<W: Write>(self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "{}", self)
+    }
+
+    fn to_le_bytes(self) -> [u8; 8] {
+        self.to_le_bytes()
+    }
+}
+
+impl OutputValue for i64 {
+    fn write_text<W: Write>(self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "{}", self)
+    }
+
+    fn to_le_bytes(self) -> [u8; 8] {
+        self.to_le_bytes()
+    }
+}
+
+/// Written as `1` and `0`.
+impl OutputValue for bool {
+    fn write_text<W: Write>(self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "{}", u8::from(self))
+    }
+
+    fn to_le_bytes(self) -> [u8; 8] {
+        u64::from(self).to_le_bytes()
+    }
+}
+
+/// Writes the value of every node to `path`, the index of a value is its node id.
+pub fn write_output<T: OutputValue>(path: &Path, format: OutputFormat, values: &[T]) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_values(&mut out, format, values)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Writes one `source target` line per relationship to `path`.
+pub fn write_edge_list<I>(path: &Path, edges: I) -> Result<()>
+where
+    I: IntoIterator<Item = (usize, usize)>,
+{
+    let mut out = BufWriter::new(File::create(path)?);
+    write_edges(&mut out, edges)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn write_edges<W: Write, I>(out: &mut W, edges: I) -> std::io::Result<()>
+where
+    I: IntoIterator<Item = (usize, usize)>,
+{
+    for (source, target) in edges {
+        writeln!(out, "{} {}", source, target)?;
+    }
+    Ok(())
+}
+
+fn write_values<W: Write, T: OutputValue>(
+    out: &mut W,
+    format: OutputFormat,
+    values: &[T],
+) -> std::io::Result<()> {
+    match format {
+        OutputFormat::Csv => {
+            writeln!(out, "node,value")?;
+            for (node, &value) in values.iter().enumerate() {
+                write!(out, "{},", node)?;
+                value.write_text(out)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Text => {
+            for &value in values {
+                value.write_text(out)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Binary => {
+            for &value in values {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written<T: OutputValue>(format: OutputFormat, values: &[T]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_values(&mut out, format, values).unwrap();
+        out
+    }
+
+    #[test]
+    fn output_csv() {
+        let out = written(OutputFormat::Csv, &[4_usize, 2, usize::MAX]);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "node,value\n0,4\n1,2\n2,18446744073709551615\n"
+        );
+    }
+
+    #[test]
+    fn output_text() {
+        let out = written(OutputFormat::Text, &[0.5_f64, 1.0]);
+        assert_eq!(String::from_utf8(out).unwrap(), "0.5\n1\n");
+
+        let out = written(OutputFormat::Text, &[true, false]);
+        assert_eq!(String::from_utf8(out).unwrap(), "1\n0\n");
+    }
+
+    #[test]
+    fn output_binary() {
+        let out = written(OutputFormat::Binary, &[1_usize, 258]);
+        assert_eq!(out, [1, 0, 0, 0, 0, 0, 0, 0, 2, 1, 0, 0, 0, 0, 0, 0]);
+
+        let out = written(OutputFormat::Binary, &[0.25_f64]);
+        assert_eq!(out, 0.25_f64.to_le_bytes());
+    }
+
+    #[test]
+    fn output_edge_list() {
+        let mut out = Vec::new();
+        write_edges(&mut out, vec![(0, 1), (1, 2)]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "0 1\n1 2\n");
+    }
+
+    #[test]
+    fn output_format_from_path() {
+        assert_eq!(
+            OutputFormat::from_path(Path::new("cc.csv")),
+            OutputFormat::Csv
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("cc.bin")),
+            OutputFormat::Binary
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("cc.txt")),
+            OutputFormat::Text
+        );
+        assert_eq!(OutputFormat::from_path(Path::new("cc")), OutputFormat::Text);
+    }
+}
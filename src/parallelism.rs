@@ -0,0 +1,74 @@
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+/// The thread pools an algorithm runs on.
+///
+/// The algorithms use whatever pool they are called from, so calling them
+/// within [`Parallelism::install`] runs them on the chosen pools.
+#[derive(Debug)]
+pub enum Parallelism {
+    /// Rayon's global pool.
+    Global,
+    /// A single pool of the caller.
+    Pool(ThreadPool),
+    /// One run on every pool, e.g. to measure the speedup of more threads.
+    Sweep(Vec<ThreadPool>),
+}
+
+impl Parallelism {
+    /// A sweep over pools with 1, 2, 4, ... threads, up to and including `max_threads`.
+    ///
+    /// `builder` is called with the number of threads of every pool.
+    pub fn sweep<F>(max_threads: usize, builder: F) -> Result<Self, ThreadPoolBuildError>
+    where
+        F: Fn(usize) -> ThreadPoolBuilder,
+    {
+        let mut thread_counts = std::iter::successors(Some(1_usize), |t| Some(t * 2))
+            .take_while(|&t| t < max_threads)
+            .collect::<Vec<_>>();
+        thread_counts.push(max_threads.max(1));
+
+        let pools = thread_counts
+            .into_iter()
+            .map(|threads| builder(threads).build())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Parallelism::Sweep(pools))
+    }
+
+    /// Calls `run` once on every pool and returns the number of threads
+    /// and the result of every run, in the order of the pools.
+    pub fn install<F, R>(&self, run: F) -> Vec<(usize, R)>
+    where
+        F: Fn() -> R + Send + Sync,
+        R: Send,
+    {
+        let run = || (rayon::current_num_threads(), run());
+        match self {
+            Parallelism::Global => vec![run()],
+            Parallelism::Pool(pool) => vec![pool.install(run)],
+            Parallelism::Sweep(pools) => pools.iter().map(|pool| pool.install(run)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallelism_install() {
+        let runs = Parallelism::Global.install(|| 42);
+        assert_eq!(runs, vec![(rayon::current_num_threads(), 42)]);
+
+        let pool = ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+        let runs = Parallelism::Pool(pool).install(rayon::current_num_threads);
+        assert_eq!(runs, vec![(3, 3)]);
+
+        let sweep = Parallelism::sweep(5, |threads| ThreadPoolBuilder::new().num_threads(threads));
+        let runs = sweep.unwrap().install(|| ());
+        let threads = runs
+            .into_iter()
+            .map(|(threads, _)| threads)
+            .collect::<Vec<_>>();
+        assert_eq!(threads, vec![1, 2, 4, 5]);
+    }
+}
@@ -0,0 +1,388 @@
+//! Parallel building blocks for the engine.
+//!
+//! All functions split their input into blocks of [`BLOCK_SIZE`] elements,
+//! compute per block results in parallel and combine them with a sequential
+//! pass over the (few) blocks.
+
+use rayon::prelude::*;
+use std::mem::MaybeUninit;
+
+/// Number of elements that are processed by a single task.
+pub const BLOCK_SIZE: usize = 2048;
+
+/// Replaces every value with the sum of all values before it and returns the total sum.
+///
+/// ```text
+/// before [1 3 3  7]
+/// after  [0 1 4  7], returns 14
+/// ```
+pub fn scan(values: &mut [usize]) -> usize {
+    if values.len() <= BLOCK_SIZE {
+        return scan_sequential(values, 0);
+    }
+
+    let mut block_sums = values
+        .par_chunks(BLOCK_SIZE)
+        .map(|block| block.iter().sum::<usize>())
+        .collect::<Vec<_>>();
+    let total = scan_sequential(&mut block_sums, 0);
+
+    values
+        .par_chunks_mut(BLOCK_SIZE)
+        .zip(block_sums.into_par_iter())
+        .for_each(|(block, offset)| {
+            scan_sequential(block, offset);
+        });
+
+    total
+}
+
+fn scan_sequential(values: &mut [usize], mut sum: usize) -> usize {
+    for value in values.iter_mut() {
+        let next = sum + *value;
+        *value = sum;
+        sum = next;
+    }
+    sum
+}
+
+/// Returns all values whose flag is set, in their original order.
+pub fn pack<T: Copy + Send + Sync>(values: &[T], flags: &[bool]) -> Vec<T> {
+    let mut packed = Vec::new();
+    pack_into(values, flags, &mut packed);
+    packed
+}
+
+/// Like [`pack`], but replaces the contents of `packed` instead of allocating.
+pub fn pack_into<T: Copy + Send + Sync>(values: &[T], flags: &[bool], packed: &mut Vec<T>) {
+    assert_eq!(values.len(), flags.len(), "every value needs a flag");
+    pack_with(flags, packed, |i| values[i]);
+}
+
+/// Returns the indices of all set flags in ascending order.
+pub fn pack_index(flags: &[bool]) -> Vec<usize> {
+    let mut indices = Vec::new();
+    pack_index_into(flags, &mut indices);
+    indices
+}
+
+/// Like [`pack_index`], but replaces the contents of `indices` instead of allocating.
+pub fn pack_index_into(flags: &[bool], indices: &mut Vec<usize>) {
+    pack_with(flags, indices, |i| i);
+}
+
+/// Returns all values that match `predicate`, in their original order.
+///
+/// The predicate is called exactly once per value.
+pub fn filter<T, P>(values: &[T], predicate: P) -> Vec<T>
+where
+    T: Copy + Send + Sync,
+    P: Fn(&T) -> bool + Send + Sync,
+{
+    let mut flags = Vec::with_capacity(values.len());
+    values
+        .par_iter()
+        .map(predicate)
+        .collect_into_vec(&mut flags);
+    pack(values, &flags)
+}
+
+/// Concatenates all `parts` into `flat`, replacing its contents.
+pub fn flatten_into<T: Copy + Send + Sync>(parts: &[Vec<T>], flat: &mut Vec<T>) {
+    let mut offsets = parts.iter().map(Vec::len).collect::<Vec<_>>();
+    let len = scan(&mut offsets);
+
+    flat.clear();
+    flat.reserve(len);
+
+    let mut targets = Vec::with_capacity(parts.len());
+    let mut spare = &mut flat.spare_capacity_mut()[..len];
+    for part in parts {
+        let (target, rest) = spare.split_at_mut(part.len());
+        targets.push(target);
+        spare = rest;
+    }
+
+    parts
+        .par_iter()
+        .zip(targets.into_par_iter())
+        .for_each(|(part, target)| {
+            for (target, &value) in target.iter_mut().zip(part) {
+                target.write(value);
+            }
+        });
+
+    // SAFETY: every slot up to `len` was written by exactly one part
+    unsafe { flat.set_len(len) };
+}
+
+/// Counts the occurrences of every key and returns the distinct keys
+/// together with their count, sorted by key.
+pub fn histogram(keys: &[usize]) -> Vec<(usize, usize)> {
+    let pairs = keys.par_iter().map(|&key| (key, 1)).collect::<Vec<_>>();
+    let mut counts = reduce_by_key(&pairs, |left, right| left + right);
+    counts.par_sort_unstable_by_key(|&(key, _)| key);
+    counts
+}
+
+/// Upper bound for the number of buckets and blocks of [`reduce_by_key`].
+const MAX_BUCKETS: usize = 1024;
+
+/// Combines the values of all pairs with the same key and returns every distinct key
+/// together with its combined value, in no particular order.
+///
+/// `reduce` must be associative and commutative. The pairs are semisorted by hashing
+/// their keys into buckets, so only the pairs within a bucket have to be sorted.
+pub fn reduce_by_key<V, F>(pairs: &[(usize, V)], reduce: F) -> Vec<(usize, V)>
+where
+    V: Copy + Send + Sync,
+    F: Fn(V, V) -> V + Send + Sync,
+{
+    let bucket_count = pairs
+        .len()
+        .div_ceil(BLOCK_SIZE)
+        .next_power_of_two()
+        .min(MAX_BUCKETS);
+    let block_size = pairs.len().div_ceil(MAX_BUCKETS).max(BLOCK_SIZE);
+    let bucket_bits = bucket_count.trailing_zeros();
+    let bucket = |key: usize| match bucket_bits {
+        0 => 0,
+        bits => ((key as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - bits)) as usize,
+    };
+
+    let counts = pairs
+        .par_chunks(block_size)
+        .map(|block| {
+            let mut counts = vec![0; bucket_count];
+            for &(key, _) in block {
+                counts[bucket(key)] += 1;
+            }
+            counts
+        })
+        .collect::<Vec<_>>();
+
+    // every bucket holds the pairs of all blocks, so that it can be reduced on its own
+    let mut semisorted = Vec::with_capacity(pairs.len());
+    let mut bucket_lens = vec![0; bucket_count];
+    let mut targets = counts
+        .iter()
+        .map(|_| Vec::with_capacity(bucket_count))
+        .collect::<Vec<_>>();
+    let mut spare = &mut semisorted.spare_capacity_mut()[..pairs.len()];
+    for (bucket, bucket_len) in bucket_lens.iter_mut().enumerate() {
+        for (block, counts) in counts.iter().enumerate() {
+            let (target, rest) = spare.split_at_mut(counts[bucket]);
+            targets[block].push(target.iter_mut());
+            spare = rest;
+            *bucket_len += counts[bucket];
+        }
+    }
+
+    pairs
+        .par_chunks(block_size)
+        .zip(targets.into_par_iter())
+        .for_each(|(block, mut targets)| {
+            for &pair in block {
+                targets[bucket(pair.0)]
+                    .next()
+                    .expect("block has more pairs in a bucket than counted")
+                    .write(pair);
+            }
+        });
+
+    // SAFETY: every block wrote exactly as many pairs into every bucket as it counted
+    unsafe { semisorted.set_len(pairs.len()) };
+
+    let mut buckets = Vec::with_capacity(bucket_count);
+    let mut rest = semisorted.as_mut_slice();
+    for bucket_len in bucket_lens {
+        let (bucket, next) = rest.split_at_mut(bucket_len);
+        buckets.push(bucket);
+        rest = next;
+    }
+
+    let parts = buckets
+        .into_par_iter()
+        .map(|bucket| {
+            bucket.sort_unstable_by_key(|&(key, _)| key);
+            let mut reduced: Vec<(usize, V)> = Vec::new();
+            for &(key, value) in bucket.iter() {
+                match reduced.last_mut() {
+                    Some((last, combined)) if *last == key => *combined = reduce(*combined, value),
+                    _ => reduced.push((key, value)),
+                }
+            }
+            reduced
+        })
+        .collect::<Vec<_>>();
+
+    let mut reduced = Vec::new();
+    flatten_into(&parts, &mut reduced);
+    reduced
+}
+
+/// Writes `value(i)` for every set flag `i` into `packed`.
+fn pack_with<T, F>(flags: &[bool], packed: &mut Vec<T>, value: F)
+where
+    T: Copy + Send + Sync,
+    F: Fn(usize) -> T + Send + Sync,
+{
+    let mut offsets = flags
+        .par_chunks(BLOCK_SIZE)
+        .map(|block| block.iter().filter(|&&flag| flag).count())
+        .collect::<Vec<_>>();
+    let len = scan(&mut offsets);
+
+    packed.clear();
+    packed.reserve(len);
+
+    let mut targets: Vec<&mut [MaybeUninit<T>]> = Vec::with_capacity(offsets.len());
+    let mut spare = &mut packed.spare_capacity_mut()[..len];
+    for (block, &offset) in offsets.iter().enumerate() {
+        let next_offset = offsets.get(block + 1).copied().unwrap_or(len);
+        let (target, rest) = spare.split_at_mut(next_offset - offset);
+        targets.push(target);
+        spare = rest;
+    }
+
+    flags
+        .par_chunks(BLOCK_SIZE)
+        .zip(targets.into_par_iter())
+        .enumerate()
+        .for_each(|(block, (flags, target))| {
+            let start = block * BLOCK_SIZE;
+            let mut target = target.iter_mut();
+            for (i, _) in flags.iter().enumerate().filter(|(_, &flag)| flag) {
+                target
+                    .next()
+                    .expect("block has more set flags than counted")
+                    .write(value(start + i));
+            }
+        });
+
+    // SAFETY: every block wrote exactly as many values as it counted
+    unsafe { packed.set_len(len) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn large() -> Vec<usize> {
+        (0..3 * BLOCK_SIZE + 42).map(|i| (i * 7919) % 13).collect()
+    }
+
+    #[quickcheck]
+    fn test_scan(values: Vec<u16>) -> bool {
+        let mut values = values.into_iter().map(usize::from).collect::<Vec<_>>();
+        let mut expected = values.clone();
+        let expected_total = scan_sequential(&mut expected, 0);
+        scan(&mut values) == expected_total && values == expected
+    }
+
+    #[test]
+    fn test_scan_blocks() {
+        let mut values = large();
+        let mut expected = values.clone();
+        let expected_total = scan_sequential(&mut expected, 0);
+        assert_eq!(scan(&mut values), expected_total);
+        assert_eq!(values, expected);
+    }
+
+    #[quickcheck]
+    fn test_pack(values: Vec<(usize, bool)>) -> bool {
+        let (values, flags): (Vec<_>, Vec<_>) = values.into_iter().unzip();
+        let expected = values
+            .iter()
+            .zip(&flags)
+            .filter(|(_, &flag)| flag)
+            .map(|(&v, _)| v)
+            .collect::<Vec<_>>();
+        pack(&values, &flags) == expected
+    }
+
+    #[test]
+    fn test_pack_blocks() {
+        let values = large();
+        let flags = values.iter().map(|v| v % 3 == 0).collect::<Vec<_>>();
+        let expected = values
+            .iter()
+            .copied()
+            .filter(|v| v % 3 == 0)
+            .collect::<Vec<_>>();
+        let mut packed = vec![1, 2, 3];
+        pack_into(&values, &flags, &mut packed);
+        assert_eq!(packed, expected);
+    }
+
+    #[quickcheck]
+    fn test_pack_index(flags: Vec<bool>) -> bool {
+        let expected = (0..flags.len()).filter(|&i| flags[i]).collect::<Vec<_>>();
+        pack_index(&flags) == expected
+    }
+
+    #[test]
+    fn test_pack_index_blocks() {
+        let flags = large().into_iter().map(|v| v < 4).collect::<Vec<_>>();
+        let expected = (0..flags.len()).filter(|&i| flags[i]).collect::<Vec<_>>();
+        assert_eq!(pack_index(&flags), expected);
+    }
+
+    #[quickcheck]
+    fn test_filter(values: Vec<usize>) -> bool {
+        let expected = values
+            .iter()
+            .copied()
+            .filter(|v| v % 2 == 0)
+            .collect::<Vec<_>>();
+        filter(&values, |v| v % 2 == 0) == expected
+    }
+
+    #[quickcheck]
+    fn test_flatten_into(parts: Vec<Vec<usize>>) -> bool {
+        let expected = parts.concat();
+        let mut flat = vec![42];
+        flatten_into(&parts, &mut flat);
+        flat == expected
+    }
+
+    #[quickcheck]
+    fn test_histogram(keys: Vec<u8>) -> bool {
+        let keys = keys.into_iter().map(usize::from).collect::<Vec<_>>();
+        let mut expected = std::collections::BTreeMap::new();
+        for &key in keys.iter() {
+            *expected.entry(key).or_insert(0) += 1;
+        }
+        histogram(&keys) == expected.into_iter().collect::<Vec<_>>()
+    }
+
+    #[quickcheck]
+    fn test_reduce_by_key(pairs: Vec<(u8, u16)>) -> bool {
+        let pairs = pairs
+            .into_iter()
+            .map(|(key, value)| (usize::from(key), u64::from(value)))
+            .collect::<Vec<_>>();
+        let mut expected = std::collections::BTreeMap::new();
+        for &(key, value) in pairs.iter() {
+            *expected.entry(key).or_insert(0) += value;
+        }
+        let mut reduced = reduce_by_key(&pairs, |left, right| left + right);
+        reduced.sort_unstable();
+        reduced == expected.into_iter().collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn test_reduce_by_key_buckets() {
+        // enough pairs for many blocks and buckets, with one heavy key
+        let pairs = (0..MAX_BUCKETS * BLOCK_SIZE + 42)
+            .map(|i| (if i % 2 == 0 { 7 } else { i % 100_003 }, 1_usize))
+            .collect::<Vec<_>>();
+        let mut expected = std::collections::BTreeMap::new();
+        for &(key, value) in pairs.iter() {
+            *expected.entry(key).or_insert(0) += value;
+        }
+        let mut reduced = reduce_by_key(&pairs, |left, right| left + right);
+        reduced.sort_unstable();
+        assert_eq!(reduced, expected.into_iter().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,787 @@
+use ligrust::{
+    algos::{
+        self, BcConfig, BcSources, BellmanFordConfig, BfsConfig, BfsTree, CcConfig,
+        DeltaSteppingConfig, KCore, KCoreConfig, MisConfig, NodeValues, OutputFormat, OutputValue,
+        PageRankConfig, PageRankDeltaConfig, Parallelism, RadiiConfig, RadiiEstimate, RunControl,
+        RunStats, ShortestPaths, TriangleConfig, Triangles,
+    },
+    graph::{
+        self, load_graph, load_weighted_graph, open_graph, Graph, Transposed, Weight, WeightedGraph,
+    },
+    ligra::Workspace,
+    Result,
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// How the engine trace of a run is written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Table,
+    JsonLines,
+}
+
+impl FromStr for TraceFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "table" => Ok(TraceFormat::Table),
+            "json" => Ok(TraceFormat::JsonLines),
+            _ => bail!("invalid trace format {:?}, use either table or json", s),
+        }
+    }
+}
+
+fn workspace(trace: Option<TraceFormat>) -> Workspace {
+    match trace {
+        Some(_) => Workspace::traced(),
+        None => Workspace::new(),
+    }
+}
+
+fn write_trace(workspace: &Workspace, format: Option<TraceFormat>) -> Result<()> {
+    if let (Some(trace), Some(format)) = (workspace.trace(), format) {
+        let out = io::stderr();
+        let out = out.lock();
+        match format {
+            TraceFormat::Table => trace.write_table(out)?,
+            TraceFormat::JsonLines => trace.write_json_lines(out)?,
+        }
+    }
+    Ok(())
+}
+
+/// Runs `run` on every pool of `parallelism` and prints the speedups of a sweep.
+/// Returns the result of the last run.
+fn run_parallel<F, R>(parallelism: &Parallelism, run: F) -> Result<R>
+where
+    F: Fn() -> Result<(Duration, R)> + Send + Sync,
+    R: Send,
+{
+    let mut timings = Vec::new();
+    let mut last = None;
+    for (threads, result) in parallelism.install(run) {
+        let (elapsed, result) = result?;
+        timings.push((threads, elapsed));
+        last = Some(result);
+    }
+    let last = match last {
+        Some(last) => last,
+        None => bail!("no thread pools to run on"),
+    };
+
+    if let Parallelism::Sweep(_) = parallelism {
+        let baseline = timings[0].1;
+        println!("{:>8} {:>14} {:>8}", "threads", "time", "speedup");
+        for (threads, elapsed) in timings {
+            println!(
+                "{:>8} {:>14} {:>8.2}",
+                threads,
+                format!("{:?}", elapsed),
+                baseline.as_secs_f64() / elapsed.as_secs_f64()
+            );
+        }
+    }
+
+    Ok(last)
+}
+
+/// Where the per-node results of an algorithm are written to.
+pub struct Output {
+    pub path: PathBuf,
+    pub format: OutputFormat,
+}
+
+/// Options that are shared by all algorithm subcommands.
+pub struct RunOptions {
+    /// input file in the binary format written by `parse`
+    pub input: PathBuf,
+    /// run on the reverse graph
+    pub reverse: bool,
+    /// print a trace of every engine call to stderr
+    pub trace: Option<TraceFormat>,
+    /// write the per-node results to a file
+    pub output: Option<Output>,
+}
+
+/// What an algorithm returns from a single run.
+pub trait RunResult: Send {
+    fn stats(&self) -> &RunStats;
+}
+
+impl<T: Send> RunResult for NodeValues<T> {
+    fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+}
+
+impl RunResult for BfsTree {
+    fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+}
+
+impl RunResult for RadiiEstimate {
+    fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+}
+
+/// An algorithm of the library that can be run from the command line.
+pub trait Algorithm: Sync {
+    type Output: RunResult;
+
+    /// Used in the summary that is printed after every run.
+    const NAME: &'static str;
+
+    /// Checks the configuration against the loaded graph before running.
+    fn validate<G: Graph>(&self, _graph: &G) -> Result<()> {
+        Ok(())
+    }
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> Self::Output;
+
+    /// Writes the result of the last run on `graph` to the outputs of `options`.
+    fn write<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        output: Self::Output,
+        options: &RunOptions,
+    ) -> Result<()>;
+}
+
+/// Like [`Algorithm`], for algorithms that need relationship weights.
+pub trait WeightedAlgorithm: Sync {
+    type Output: RunResult;
+
+    const NAME: &'static str;
+
+    fn validate<G: WeightedGraph>(&self, _graph: &G) -> Result<()> {
+        Ok(())
+    }
+
+    fn run<G: WeightedGraph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> Self::Output;
+
+    fn write(&self, output: Self::Output, options: &RunOptions) -> Result<()>;
+}
+
+fn write_values<T: OutputValue>(values: &[T], options: &RunOptions) -> Result<()> {
+    match &options.output {
+        Some(output) => algos::write_output(&output.path, output.format, values),
+        None => Ok(()),
+    }
+}
+
+impl Algorithm for CcConfig {
+    type Output = NodeValues<usize>;
+
+    const NAME: &'static str = "cc";
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> NodeValues<usize> {
+        algos::connected_components_with(graph, self, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        _graph: &G,
+        output: NodeValues<usize>,
+        options: &RunOptions,
+    ) -> Result<()> {
+        write_values(&output.values, options)
+    }
+}
+
+fn validate_sources<G: Graph>(sources: &[usize], graph: &G) -> Result<()> {
+    ensure!(!sources.is_empty(), "at least one source is needed");
+    for &source in sources {
+        ensure!(
+            source < graph.node_count(),
+            "source {} is not a node of the graph with {} nodes",
+            source,
+            graph.node_count()
+        );
+    }
+    Ok(())
+}
+
+/// Which per-node values of a breadth-first search are written to the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BfsValues {
+    Parents,
+    Distances,
+    Sources,
+}
+
+impl FromStr for BfsValues {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "parents" => Ok(BfsValues::Parents),
+            "distances" => Ok(BfsValues::Distances),
+            "sources" => Ok(BfsValues::Sources),
+            _ => bail!(
+                "invalid bfs values {:?}, use either parents, distances or sources",
+                s
+            ),
+        }
+    }
+}
+
+/// The `bfs` subcommand.
+pub struct Bfs {
+    pub config: BfsConfig,
+    /// the values that are written to the output
+    pub values: BfsValues,
+    /// write the relationships of the search tree to this file
+    pub tree: Option<PathBuf>,
+}
+
+impl Algorithm for Bfs {
+    type Output = BfsTree;
+
+    const NAME: &'static str = "bfs";
+
+    fn validate<G: Graph>(&self, graph: &G) -> Result<()> {
+        validate_sources(&self.config.sources, graph)
+    }
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> BfsTree {
+        algos::bfs_tree_with(graph, &self.config, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        _graph: &G,
+        output: BfsTree,
+        options: &RunOptions,
+    ) -> Result<()> {
+        if let Some(tree) = &self.tree {
+            algos::write_edge_list(tree, output.edges())?;
+        }
+        let values = match self.values {
+            BfsValues::Parents => &output.parents,
+            BfsValues::Distances => &output.distances,
+            BfsValues::Sources => &output.sources,
+        };
+        write_values(values, options)
+    }
+}
+
+impl Algorithm for BcConfig {
+    type Output = NodeValues<f64>;
+
+    const NAME: &'static str = "bc";
+
+    fn validate<G: Graph>(&self, graph: &G) -> Result<()> {
+        match &self.sources {
+            BcSources::All => Ok(()),
+            BcSources::Nodes(sources) => validate_sources(sources, graph),
+            BcSources::Sample(samples) => {
+                ensure!(*samples > 0, "at least one sample is needed");
+                Ok(())
+            }
+        }
+    }
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> NodeValues<f64> {
+        algos::betweenness_centrality_with(graph, self, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        _graph: &G,
+        output: NodeValues<f64>,
+        options: &RunOptions,
+    ) -> Result<()> {
+        write_values(&output.values, options)
+    }
+}
+
+impl Algorithm for RadiiConfig {
+    type Output = RadiiEstimate;
+
+    const NAME: &'static str = "radii";
+
+    fn validate<G: Graph>(&self, _graph: &G) -> Result<()> {
+        ensure!(
+            (1..=64).contains(&self.samples),
+            "radii needs between 1 and 64 samples, got {}",
+            self.samples
+        );
+        Ok(())
+    }
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> RadiiEstimate {
+        algos::radii_with(graph, self, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        _graph: &G,
+        output: RadiiEstimate,
+        options: &RunOptions,
+    ) -> Result<()> {
+        println!("estimated diameter: {}", output.diameter);
+        write_values(&output.eccentricities, options)
+    }
+}
+
+impl RunResult for KCore {
+    fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+}
+
+/// The `kcore` subcommand.
+pub struct KCoreDecomposition {
+    pub config: KCoreConfig,
+    /// write the `k`-core for this `k` to `subgraph`
+    pub core: Option<usize>,
+    /// output file for the `k`-core, in the binary format written by `parse`
+    pub subgraph: Option<PathBuf>,
+}
+
+impl Algorithm for KCoreDecomposition {
+    type Output = KCore;
+
+    const NAME: &'static str = "kcore";
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> KCore {
+        algos::k_core_with(graph, &self.config, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(&self, graph: &G, output: KCore, options: &RunOptions) -> Result<()> {
+        println!("degeneracy: {}", output.degeneracy);
+        if let (Some(k), Some(path)) = (self.core, &self.subgraph) {
+            let core = output.subgraph(graph, k);
+            step("writing core", || {
+                graph::dump(core, BufWriter::new(File::create(path)?))
+            })?;
+        }
+        write_values(&output.coreness, options)
+    }
+}
+
+/// The `mis` subcommand.
+pub struct Mis {
+    pub config: MisConfig,
+    /// check that the result is a maximal independent set
+    pub verify: bool,
+}
+
+impl Algorithm for Mis {
+    type Output = NodeValues<bool>;
+
+    const NAME: &'static str = "mis";
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> NodeValues<bool> {
+        algos::maximal_independent_set_with(graph, &self.config, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        output: NodeValues<bool>,
+        options: &RunOptions,
+    ) -> Result<()> {
+        let size = output.values.iter().filter(|&&in_set| in_set).count();
+        println!("independent set with {} nodes", size);
+        if self.verify {
+            algos::verify_independent_set(graph, &output.values)?;
+            println!("verified independence and maximality");
+        }
+        write_values(&output.values, options)
+    }
+}
+
+impl RunResult for Triangles {
+    fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+}
+
+impl Algorithm for TriangleConfig {
+    type Output = Triangles;
+
+    const NAME: &'static str = "triangles";
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> Triangles {
+        algos::triangle_count_with(graph, self, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        _graph: &G,
+        output: Triangles,
+        options: &RunOptions,
+    ) -> Result<()> {
+        println!("triangles: {}", output.count);
+        match &output.node_counts {
+            Some(node_counts) => write_values(node_counts, options),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Algorithm for PageRankConfig {
+    type Output = NodeValues<f64>;
+
+    const NAME: &'static str = "page rank";
+
+    fn validate<G: Graph>(&self, _graph: &G) -> Result<()> {
+        validate_damping(self.damping)
+    }
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> NodeValues<f64> {
+        algos::page_rank_with(graph, self, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        _graph: &G,
+        output: NodeValues<f64>,
+        options: &RunOptions,
+    ) -> Result<()> {
+        write_values(&output.values, options)
+    }
+}
+
+fn validate_damping(damping: f64) -> Result<()> {
+    ensure!(
+        (0.0..=1.0).contains(&damping),
+        "damping must be between 0 and 1, got {}",
+        damping
+    );
+    Ok(())
+}
+
+impl Algorithm for PageRankDeltaConfig {
+    type Output = NodeValues<f64>;
+
+    const NAME: &'static str = "page rank delta";
+
+    fn validate<G: Graph>(&self, _graph: &G) -> Result<()> {
+        validate_damping(self.damping)
+    }
+
+    fn run<G: Graph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> NodeValues<f64> {
+        algos::page_rank_delta_with(graph, self, workspace, control)
+    }
+
+    fn write<G: Graph + Sync>(
+        &self,
+        _graph: &G,
+        output: NodeValues<f64>,
+        options: &RunOptions,
+    ) -> Result<()> {
+        write_values(&output.values, options)
+    }
+}
+
+impl RunResult for ShortestPaths {
+    fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+}
+
+/// The algorithm of the `sssp` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SsspAlgorithm {
+    BellmanFord,
+    DeltaStepping,
+}
+
+impl FromStr for SsspAlgorithm {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bellman-ford" => Ok(SsspAlgorithm::BellmanFord),
+            "delta-stepping" => Ok(SsspAlgorithm::DeltaStepping),
+            _ => bail!(
+                "invalid sssp algorithm {:?}, use either bellman-ford or delta-stepping",
+                s
+            ),
+        }
+    }
+}
+
+/// Which per-node values of a shortest path search are written to the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SsspValues {
+    Distances,
+    Predecessors,
+}
+
+impl FromStr for SsspValues {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "distances" => Ok(SsspValues::Distances),
+            "predecessors" => Ok(SsspValues::Predecessors),
+            _ => bail!(
+                "invalid sssp values {:?}, use either distances or predecessors",
+                s
+            ),
+        }
+    }
+}
+
+/// The `sssp` subcommand.
+pub struct Sssp {
+    pub algorithm: SsspAlgorithm,
+    pub source: usize,
+    /// the bucket width of delta-stepping
+    pub delta: Option<Weight>,
+    /// the values that are written to the output
+    pub values: SsspValues,
+}
+
+impl WeightedAlgorithm for Sssp {
+    type Output = ShortestPaths;
+
+    const NAME: &'static str = "sssp";
+
+    fn validate<G: WeightedGraph>(&self, graph: &G) -> Result<()> {
+        validate_sources(&[self.source], graph)?;
+        if self.algorithm == SsspAlgorithm::DeltaStepping {
+            if let Some(delta) = self.delta {
+                ensure!(delta > 0, "delta must be positive, got {}", delta);
+            }
+            for node in 0..graph.node_count() {
+                if let Some(weight) = graph.out_weights(node).iter().find(|&&w| w < 0) {
+                    bail!(
+                        "delta-stepping needs non-negative weights, node {} has a relationship of weight {}",
+                        node,
+                        weight
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run<G: WeightedGraph + Sync>(
+        &self,
+        graph: &G,
+        workspace: &mut Workspace,
+        control: &RunControl,
+    ) -> ShortestPaths {
+        match self.algorithm {
+            SsspAlgorithm::BellmanFord => {
+                let config = BellmanFordConfig::new(self.source);
+                algos::bellman_ford_with(graph, &config, workspace, control)
+            }
+            SsspAlgorithm::DeltaStepping => {
+                let mut config = DeltaSteppingConfig::new(self.source);
+                if let Some(delta) = self.delta {
+                    config = config.with_delta(delta);
+                }
+                algos::delta_stepping_with(graph, &config, workspace, control)
+            }
+        }
+    }
+
+    fn write(&self, output: ShortestPaths, options: &RunOptions) -> Result<()> {
+        ensure!(
+            !output.negative_cycle,
+            "a negative cycle is reachable from source {}",
+            self.source
+        );
+        match self.values {
+            SsspValues::Distances => write_values(&output.distances, options),
+            SsspValues::Predecessors => write_values(&output.predecessors, options),
+        }
+    }
+}
+
+fn report(name: &str, node_count: usize, elapsed: Duration, stats: &RunStats) {
+    match stats.stopped {
+        None => println!("{} done with {} nodes: {:?}", name, node_count, elapsed),
+        Some(stop) => println!(
+            "{} {} after {} iterations with {} nodes: {:?}",
+            name, stop, stats.iterations, node_count, elapsed
+        ),
+    }
+}
+
+/// Runs a single step of loading or writing a graph and reports how long it took.
+fn step<R>(name: &str, step: impl FnOnce() -> Result<R>) -> Result<R> {
+    let start = Instant::now();
+    let result = step()?;
+    println!("{}: {:?}", name, start.elapsed());
+    Ok(result)
+}
+
+/// Parses the text graph at `input` and writes it in the binary format to `output`.
+pub fn parse(input: PathBuf, output: PathBuf) -> Result<()> {
+    step("parsing graph", || graph::parse(input, output))
+}
+
+/// Runs `run` on every pool of `parallelism` and reports every run.
+fn timed<R, F>(
+    name: &str,
+    node_count: usize,
+    options: &RunOptions,
+    parallelism: &Parallelism,
+    run: F,
+) -> Result<R>
+where
+    R: RunResult,
+    F: Fn(&mut Workspace) -> R + Send + Sync,
+{
+    run_parallel(parallelism, || {
+        let mut workspace = workspace(options.trace);
+        let start = Instant::now();
+
+        let result = run(&mut workspace);
+
+        let elapsed = start.elapsed();
+        report(name, node_count, elapsed, result.stats());
+        write_trace(&workspace, options.trace)?;
+        Ok((elapsed, result))
+    })
+}
+
+/// Loads the input graph, runs `algorithm` on it and writes the results.
+pub fn run<A: Algorithm>(
+    algorithm: &A,
+    options: RunOptions,
+    parallelism: &Parallelism,
+    control: &RunControl,
+) -> Result<()> {
+    fn run_on<A: Algorithm, G: Graph + Sync>(
+        algorithm: &A,
+        graph: &G,
+        options: &RunOptions,
+        parallelism: &Parallelism,
+        control: &RunControl,
+    ) -> Result<()> {
+        algorithm.validate(graph)?;
+        let output = timed(
+            A::NAME,
+            graph.node_count(),
+            options,
+            parallelism,
+            |workspace| algorithm.run(graph, workspace, control),
+        )?;
+        algorithm.write(graph, output, options)
+    }
+
+    let input = step("preparing input", || open_graph(options.input.clone()))?;
+    let graph = step("loading graph", || load_graph(&input))?;
+    if options.reverse {
+        run_on(
+            algorithm,
+            &Transposed::new(graph),
+            &options,
+            parallelism,
+            control,
+        )
+    } else {
+        run_on(algorithm, &graph, &options, parallelism, control)
+    }
+}
+
+/// Like [`run`], but for algorithms that need relationship weights.
+pub fn run_weighted<A: WeightedAlgorithm>(
+    algorithm: &A,
+    options: RunOptions,
+    parallelism: &Parallelism,
+    control: &RunControl,
+) -> Result<()> {
+    fn run_on<A: WeightedAlgorithm, G: WeightedGraph + Sync>(
+        algorithm: &A,
+        graph: &G,
+        options: &RunOptions,
+        parallelism: &Parallelism,
+        control: &RunControl,
+    ) -> Result<()> {
+        algorithm.validate(graph)?;
+        let output = timed(
+            A::NAME,
+            graph.node_count(),
+            options,
+            parallelism,
+            |workspace| algorithm.run(graph, workspace, control),
+        )?;
+        algorithm.write(output, options)
+    }
+
+    let input = step("preparing input", || open_graph(options.input.clone()))?;
+    let graph = step("loading graph", || load_weighted_graph(&input))?;
+    if options.reverse {
+        run_on(
+            algorithm,
+            &Transposed::new(graph),
+            &options,
+            parallelism,
+            control,
+        )
+    } else {
+        run_on(algorithm, &graph, &options, parallelism, control)
+    }
+}
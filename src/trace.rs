@@ -0,0 +1,227 @@
+//! Per-call statistics of the engine, see [`Workspace::traced`](super::Workspace::traced).
+
+use std::{
+    fmt,
+    io::{self, Write},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    RelationshipMap,
+    RelationshipMapReduce,
+    NodeFilter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sparse,
+    Dense,
+}
+
+/// What happened during a single call of an engine function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub operation: Operation,
+    /// Counts the calls of the same operation, starting at 0.
+    pub iteration: usize,
+    /// For relationship maps, the direction that was chosen for the traversal.
+    /// For node filters, the representation of the input subset.
+    pub direction: Direction,
+    pub frontier_size: usize,
+    /// The sum of the out degrees of the frontier, only for relationship maps and reductions.
+    pub out_degrees: Option<usize>,
+    /// The relationships that were looked at, only for relationship maps and reductions.
+    /// Dense traversals stop early for targets that fail the mapper's `check`.
+    pub relationships_traversed: Option<usize>,
+    pub elapsed: Duration,
+}
+
+/// Collects a [`TraceEvent`] for every call of an engine function.
+#[derive(Debug, Default, Clone)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+    /// The number of recorded calls, indexed by [`Operation::index`]
+    iterations: [usize; Operation::COUNT],
+}
+
+impl Trace {
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// The number of recorded calls of `operation`.
+    pub fn iterations(&self, operation: Operation) -> usize {
+        self.iterations[operation.index()]
+    }
+
+    pub(crate) fn push(&mut self, event: TraceEvent) {
+        self.iterations[event.operation.index()] += 1;
+        self.events.push(event);
+    }
+
+    /// Writes all events as an aligned table with a header.
+    pub fn write_table<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(
+            out,
+            "{:<23} {:>9} {:<9} {:>12} {:>12} {:>14} {:>14}",
+            "operation", "iteration", "direction", "frontier", "out_degrees", "traversed", "time"
+        )?;
+        for event in self.events.iter() {
+            writeln!(
+                out,
+                "{:<23} {:>9} {:<9} {:>12} {:>12} {:>14} {:>14}",
+                event.operation.to_string(),
+                event.iteration,
+                event.direction.to_string(),
+                event.frontier_size,
+                OrDash(event.out_degrees),
+                OrDash(event.relationships_traversed),
+                format!("{:?}", event.elapsed),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes every event as a JSON object on its own line.
+    pub fn write_json_lines<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for event in self.events.iter() {
+            writeln!(
+                out,
+                r#"{{"operation":"{}","iteration":{},"direction":"{}","frontier_size":{},"out_degrees":{},"relationships_traversed":{},"elapsed_nanos":{}}}"#,
+                event.operation,
+                event.iteration,
+                event.direction,
+                event.frontier_size,
+                OrNull(event.out_degrees),
+                OrNull(event.relationships_traversed),
+                event.elapsed.as_nanos(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Operation {
+    const COUNT: usize = 3;
+
+    fn index(self) -> usize {
+        match self {
+            Operation::RelationshipMap => 0,
+            Operation::RelationshipMapReduce => 1,
+            Operation::NodeFilter => 2,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::RelationshipMap => f.write_str("relationship_map"),
+            Operation::RelationshipMapReduce => f.write_str("relationship_map_reduce"),
+            Operation::NodeFilter => f.write_str("node_filter"),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Sparse => f.write_str("sparse"),
+            Direction::Dense => f.write_str("dense"),
+        }
+    }
+}
+
+struct OrDash(Option<usize>);
+
+impl fmt::Display for OrDash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(value) => value.fmt(f),
+            None => "-".fmt(f),
+        }
+    }
+}
+
+struct OrNull(Option<usize>);
+
+impl fmt::Display for OrNull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(value) => value.fmt(f),
+            None => f.write_str("null"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace() -> Trace {
+        let mut trace = Trace::default();
+        trace.push(TraceEvent {
+            operation: Operation::NodeFilter,
+            iteration: 0,
+            direction: Direction::Dense,
+            frontier_size: 4,
+            out_degrees: None,
+            relationships_traversed: None,
+            elapsed: Duration::from_micros(3),
+        });
+        trace.push(TraceEvent {
+            operation: Operation::RelationshipMap,
+            iteration: 0,
+            direction: Direction::Sparse,
+            frontier_size: 2,
+            out_degrees: Some(5),
+            relationships_traversed: Some(5),
+            elapsed: Duration::from_nanos(1500),
+        });
+        trace
+    }
+
+    #[test]
+    fn trace_iterations() {
+        let mut trace = trace();
+        assert_eq!(trace.iterations(Operation::NodeFilter), 1);
+        assert_eq!(trace.iterations(Operation::RelationshipMap), 1);
+        assert_eq!(trace.iterations(Operation::RelationshipMapReduce), 0);
+
+        let mut event = trace.events()[0].clone();
+        event.iteration = 1;
+        trace.push(event);
+        assert_eq!(trace.iterations(Operation::NodeFilter), 2);
+        assert_eq!(trace.iterations(Operation::RelationshipMap), 1);
+    }
+
+    #[test]
+    fn trace_json_lines() {
+        let mut out = Vec::new();
+        trace().write_json_lines(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                r#"{"operation":"node_filter","iteration":0,"direction":"dense","frontier_size":4,"out_degrees":null,"relationships_traversed":null,"elapsed_nanos":3000}"#,
+                "\n",
+                r#"{"operation":"relationship_map","iteration":0,"direction":"sparse","frontier_size":2,"out_degrees":5,"relationships_traversed":5,"elapsed_nanos":1500}"#,
+                "\n",
+            )
+        );
+    }
+
+    #[test]
+    fn trace_table() {
+        let mut out = Vec::new();
+        trace().write_table(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("operation"));
+        assert!(lines[1].starts_with("node_filter"));
+        assert!(lines[1].contains(" - "));
+        assert!(lines[2].starts_with("relationship_map"));
+        assert!(lines[2].ends_with("1.5µs"));
+    }
+}
@@ -0,0 +1,156 @@
+use super::{NodeSubset, Trace};
+use rayon::prelude::*;
+
+/// Owns the scratch buffers of the engine so that they can be reused across iterations.
+///
+/// Pass the same workspace to the `*_with` variants of the engine functions and hand
+/// node subsets that are no longer needed back via [`Workspace::recycle`].
+/// After the first iteration, the engine then rarely needs to allocate.
+#[derive(Debug, Default)]
+pub struct Workspace {
+    degrees: Vec<usize>,
+    dense: Vec<Vec<bool>>,
+    sparse: Vec<Vec<usize>>,
+    seen: Vec<bool>,
+    #[cfg(not(feature = "sparse_atomic_pack"))]
+    blocks: Vec<Vec<usize>>,
+    #[cfg(not(feature = "sparse_atomic_pack"))]
+    blocks_capacity: usize,
+    allocations: usize,
+    trace: Option<Trace>,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a workspace that also records a [`Trace`] of every engine call it is passed to.
+    pub fn traced() -> Self {
+        Self {
+            trace: Some(Trace::default()),
+            ..Self::default()
+        }
+    }
+
+    /// The recorded trace, if this workspace was created with [`Workspace::traced`].
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    pub(crate) fn trace_mut(&mut self) -> Option<&mut Trace> {
+        self.trace.as_mut()
+    }
+
+    /// The number of buffers that had to be allocated or grown so far.
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+
+    /// Keeps the buffers of `node_subset` for later use.
+    pub fn recycle(&mut self, node_subset: NodeSubset) {
+        let (dense, sparse) = node_subset.into_buffers();
+        if let Some(dense) = dense {
+            self.give_dense(dense);
+        }
+        if let Some(sparse) = sparse {
+            self.give_sparse(sparse);
+        }
+    }
+
+    /// Returns a buffer with `len` entries set to false.
+    pub(crate) fn take_dense(&mut self, len: usize) -> Vec<bool> {
+        let mut dense = self.dense.pop().unwrap_or_default();
+        self.track(dense.capacity(), len);
+        dense.clear();
+        dense.par_extend(rayon::iter::repeatn(false, len));
+        dense
+    }
+
+    /// Returns an empty buffer that is expected to hold `capacity` entries.
+    pub(crate) fn take_dense_empty(&mut self, capacity: usize) -> Vec<bool> {
+        let mut dense = self.dense.pop().unwrap_or_default();
+        self.track(dense.capacity(), capacity);
+        dense.clear();
+        dense
+    }
+
+    /// Returns an empty buffer that is expected to hold up to `capacity` entries.
+    pub(crate) fn take_sparse(&mut self, capacity: usize) -> Vec<usize> {
+        let mut sparse = self.sparse.pop().unwrap_or_default();
+        self.track(sparse.capacity(), capacity);
+        sparse.clear();
+        sparse.reserve(capacity);
+        sparse
+    }
+
+    pub(crate) fn give_dense(&mut self, dense: Vec<bool>) {
+        self.dense.push(dense);
+    }
+
+    pub(crate) fn give_sparse(&mut self, sparse: Vec<usize>) {
+        self.sparse.push(sparse);
+    }
+
+    /// Returns a flag per node that is set to false.
+    ///
+    /// Unlike [`Workspace::take_dense`], the flags are not cleared on every call,
+    /// callers must reset all flags they set before giving the buffer back.
+    pub(crate) fn take_seen(&mut self, len: usize) -> Vec<bool> {
+        let mut seen = std::mem::take(&mut self.seen);
+        if seen.len() < len {
+            self.track(seen.capacity(), len);
+            seen.clear();
+            seen.par_extend(rayon::iter::repeatn(false, len));
+        }
+        seen
+    }
+
+    pub(crate) fn give_seen(&mut self, seen: Vec<bool>) {
+        self.seen = seen;
+    }
+
+    /// Returns an empty buffer for `len` frontier degrees.
+    pub(crate) fn take_degrees(&mut self, len: usize) -> Vec<usize> {
+        let mut degrees = std::mem::take(&mut self.degrees);
+        self.track(degrees.capacity(), len);
+        degrees.clear();
+        degrees
+    }
+
+    pub(crate) fn give_degrees(&mut self, degrees: Vec<usize>) {
+        self.degrees = degrees;
+    }
+
+    /// Returns at least `count` buffers for the per-block output of a sparse traversal,
+    /// the first `count` of them are empty.
+    #[cfg(not(feature = "sparse_atomic_pack"))]
+    pub(crate) fn take_blocks(&mut self, count: usize) -> Vec<Vec<usize>> {
+        let mut blocks = std::mem::take(&mut self.blocks);
+        if blocks.len() < count {
+            self.allocations += count - blocks.len();
+            blocks.resize_with(count, Vec::new);
+        }
+        blocks[..count].iter_mut().for_each(Vec::clear);
+        self.blocks_capacity = Self::capacity(&blocks);
+        blocks
+    }
+
+    #[cfg(not(feature = "sparse_atomic_pack"))]
+    pub(crate) fn give_blocks(&mut self, blocks: Vec<Vec<usize>>) {
+        let capacity = Self::capacity(&blocks);
+        self.track(self.blocks_capacity, capacity);
+        self.blocks = blocks;
+    }
+
+    #[cfg(not(feature = "sparse_atomic_pack"))]
+    fn capacity(blocks: &[Vec<usize>]) -> usize {
+        blocks.iter().map(Vec::capacity).sum()
+    }
+
+    fn track(&mut self, capacity: usize, required: usize) {
+        if capacity < required {
+            self.allocations += 1;
+        }
+    }
+}